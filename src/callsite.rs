@@ -1,8 +1,12 @@
 mod default;
 mod empty;
 mod kind;
+mod record;
 
-use std::sync::{LazyLock, Mutex};
+use std::{
+    any::Any,
+    sync::{LazyLock, Mutex},
+};
 
 use pyo3::{
     Bound, Python,
@@ -13,7 +17,11 @@ use tracing::{Level, Metadata, Value, field::ValueSet, level_filters, warn};
 use tracing_core::{Callsite, Kind, LevelFilter, callsite::DefaultCallsite};
 
 use crate::{
-    callsite::{default::CallsiteIdentifier, empty::EmptyCallsite, kind::CallsiteKind},
+    callsite::{
+        default::{CallsiteIdentifier, CallsiteLocation},
+        empty::EmptyCallsite,
+        kind::CallsiteKind,
+    },
     ext::frame::{PyFrameMethodsExt, UnboundPyFrameMethodsExt},
     introspect::Inspector,
 };
@@ -23,6 +31,11 @@ use crate::{
 static CALLSITES: LazyLock<Mutex<RapidHashMap<CallsiteIdentifier, &'static DefaultCallsite>>> =
     LazyLock::new(Mutex::default);
 
+// taken by the thread that forks, so the child doesn't inherit the map locked by another thread
+pub fn try_lock_for_fork() -> Option<impl Any> {
+    CALLSITES.try_lock().ok()
+}
+
 pub fn leaked_callsites_count() -> usize {
     CALLSITES.lock().unwrap().len()
 }
@@ -62,24 +75,59 @@ pub fn get_or_init_callsite(
 
     let identifier = CallsiteIdentifier::new(
         // todo: ix_address can be reused if the code is dynamically compiled, probably something else should be used
-        inspector.ix_address(),
+        CallsiteLocation::Instruction(inspector.ix_address()),
         level,
         fields,
         CallsiteKind::from(kind),
     );
 
-    let mut callsites = CALLSITES.lock().unwrap();
+    get_or_insert(identifier, |identifier| {
+        default::new_callsite((frame, code), identifier.clone())
+    })
+}
 
-    if callsites.len() >= 100_000 {
+pub use record::RecordLocation;
+
+// callsites of the events that come from the stdlib logging module, which only knows the file and the line
+pub fn get_or_init_record_callsite(
+    location: RecordLocation,
+    level: Level,
+    fields: &'static [&'static str],
+) -> &'static DefaultCallsite {
+    let identifier = CallsiteIdentifier::new(
+        CallsiteLocation::Record(location),
+        level,
+        fields,
+        CallsiteKind::Event,
+    );
+
+    get_or_insert(identifier, |identifier| {
+        let CallsiteLocation::Record(location) = identifier.location() else {
+            unreachable!("the identifier was created with a record location")
+        };
+        record::new_callsite(location, level, fields)
+    })
+}
+
+// the identifier is only cloned or interned when the callsite is created
+fn get_or_insert(
+    identifier: CallsiteIdentifier,
+    init: impl FnOnce(&CallsiteIdentifier) -> &'static DefaultCallsite,
+) -> &'static DefaultCallsite {
+    let (callsite, count) = {
+        let mut callsites = CALLSITES.lock().unwrap();
+        let callsite = *callsites.entry(identifier).or_insert_with_key(init);
+        (callsite, callsites.len())
+    };
+
+    // the lock isn't held here, the warning may come back through LoggingLayer and LoggingHandler
+    if count >= 100_000 {
         warn!(
-            "there are {} callsites, are you sure you're doing the right thing? using tracing in dynamically compiled code leaks memory",
-            callsites.len()
+            "there are {count} callsites, are you sure you're doing the right thing? using tracing in dynamically compiled code leaks memory"
         );
     }
 
-    callsites
-        .entry(identifier.clone())
-        .or_insert_with(|| default::new_callsite((frame, code), identifier))
+    callsite
 }
 pub trait CallsiteAction {
     const KIND: Kind;
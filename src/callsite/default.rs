@@ -9,18 +9,26 @@ use tracing_core::{
 };
 
 use crate::{
-    callsite::{CallsiteKind, EmptyCallsite},
+    callsite::{CallsiteKind, EmptyCallsite, record::RecordLocation},
     ext::{code::PyCodeMethodsExt, frame::PyFrameMethodsExt},
     introspect::Inspector,
     leak::{Leaker, leak},
 };
 
+#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+pub enum CallsiteLocation {
+    // bytecode instruction address
+    Instruction(usize),
+    // stdlib logging records have no frame
+    Record(RecordLocation),
+}
+
 // a single address can contain multiple callsites,
 // since i can't make python code use only a single event level or constast fields
 // filename, module name and line number hopefully stay constant
 #[derive(Hash, PartialEq, Eq, Clone, Debug)]
 pub struct CallsiteIdentifier {
-    address: usize,
+    location: CallsiteLocation,
     level: Level,
     fields: &'static [&'static str],
     kind: CallsiteKind,
@@ -28,18 +36,22 @@ pub struct CallsiteIdentifier {
 
 impl CallsiteIdentifier {
     pub fn new(
-        address: usize,
+        location: CallsiteLocation,
         level: Level,
         fields: &'static [&'static str],
         kind: CallsiteKind,
     ) -> Self {
         Self {
-            address,
+            location,
             level,
             fields,
             kind,
         }
     }
+
+    pub fn location(&self) -> &CallsiteLocation {
+        &self.location
+    }
 }
 
 pub fn new_callsite(
@@ -60,8 +72,18 @@ pub fn new_callsite(
 
         let filename = leaker.leak_or_get(code.filename().to_string_lossy().into_owned());
 
-        let target = code.qualname();
-        let target = leaker.leak_or_get(target.to_string_lossy().into_owned());
+        let inspector = Inspector::new(&frame);
+
+        // prefixing the qualname with the module name, so env filter directives like `myapp.db=debug` work
+        let qualname = code.qualname();
+        let qualname = qualname.to_string_lossy();
+        let module_name = inspector.module_name();
+        let target = leaker.leak_or_get(match &module_name {
+            Some(module) => format!("{module}.{qualname}"),
+            None => qualname.into_owned(),
+        });
+        // the dotted module name, the target without it is the qualname
+        let module_path = module_name.map(|module| leaker.leak_or_get(module));
 
         let empty_callsite = EmptyCallsite::new();
 
@@ -71,9 +93,6 @@ pub fn new_callsite(
             CallsiteKind::Hint => unimplemented!(),
         });
 
-        let inspector = Inspector::new(&frame);
-
-        // using module() for both file and module for now
         let file = leaker.leak_or_get(inspector.module());
 
         leak(Metadata::new(
@@ -82,7 +101,7 @@ pub fn new_callsite(
             level,
             Some(file),
             Some(line),
-            Some(file),
+            module_path,
             FieldSet::new(fields, Identifier(empty_callsite)),
             Kind::from(kind),
         ))
@@ -0,0 +1,46 @@
+use tracing::{Level, Metadata, field::FieldSet};
+use tracing_core::{
+    Kind,
+    callsite::{DefaultCallsite, Identifier},
+};
+
+use crate::{
+    callsite::EmptyCallsite,
+    leak::{Leaker, leak},
+};
+
+// the logger name is a part of the location as it's used as the target;
+// the strings are owned, so a known callsite is found without interning them
+#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+pub struct RecordLocation {
+    pub target: String,
+    pub file: String,
+    pub line: u32,
+}
+
+// the strings are leaked only here, when the callsite is created
+pub fn new_callsite(
+    RecordLocation { target, file, line }: &RecordLocation,
+    level: Level,
+    fields: &'static [&'static str],
+) -> &'static DefaultCallsite {
+    let mut leaker = Leaker::acquire();
+    let target = leaker.leak_or_get(target.clone());
+    let file = leaker.leak_or_get(file.clone());
+    drop(leaker);
+    let name = Leaker::leak_or_get_once(format!("event {file}"));
+
+    let meta = leak(Metadata::new(
+        name,
+        target,
+        level,
+        Some(file),
+        Some(*line),
+        // the python callsites have the module name, the target is the logger name here, which doesn't contain it
+        None,
+        FieldSet::new(fields, Identifier(EmptyCallsite::new())),
+        Kind::EVENT,
+    ));
+
+    leak(DefaultCallsite::new(meta))
+}
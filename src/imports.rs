@@ -27,6 +27,38 @@ mk_import!(get_inspect_parameter_type, "inspect", "Parameter", PyType);
 
 mk_import!(get_atexit_register, "atexit", "register", PyCFunction);
 
+#[cfg(unix)]
+mk_import!(
+    get_os_register_at_fork,
+    "os",
+    "register_at_fork",
+    PyCFunction
+);
+
+mk_import!(
+    get_threading_get_ident,
+    "threading",
+    "get_ident",
+    PyCFunction
+);
+mk_import!(
+    get_threading_current_thread,
+    "threading",
+    "current_thread",
+    PyFunction
+);
+
+mk_import!(get_socket_gethostname, "socket", "gethostname", PyCFunction);
+
+mk_import!(get_logging_handler_type, "logging", "Handler", PyType);
+mk_import!(get_logging_get_logger, "logging", "getLogger", PyFunction);
+mk_import!(
+    get_traceback_format_exception,
+    "traceback",
+    "format_exception",
+    PyFunction
+);
+
 pub fn get_or_import<'py, 'a, T: PyTypeCheck>(
     py: Python<'py>,
     lock: &'a PyOnceLock<Py<T>>,
@@ -32,26 +32,34 @@ impl<'a, 'py> Inspector<'a, 'py> {
         self.code.bytecode().as_ptr() as usize + last_instruction_offset
     }
 
-    // ugly, but for some reason other attributes/functions for introspection didn't work
-    //
-    // it fails if someone changes __name__, but who would do this?
-    //
-    // maybe i'll fix it later, but anyway it's evaluated only a single time for each callsite
-    pub fn module(&self) -> String {
+    // the same as the `__name__` global, e.g. "package.module" or "__main__"
+    pub fn module_name(&self) -> Option<String> {
         // SAFETY: PyEval_GetGlobals returns an borrowed ref:
         // https://docs.python.org/3/c-api/reflection.html#c.PyEval_GetGlobals
         let globals = unsafe { Bound::from_borrowed_ptr(self.py, PyEval_GetGlobals()) };
         let mod_name = PyAnyMethods::get_item(&globals, "__name__")
             .expect("__name__ global variable must exist");
 
-        let path_length = if mod_name.is_none() {
-            0
+        if mod_name.is_none() {
+            None
         } else {
             let s: Bound<PyString> = mod_name
                 .extract()
                 .expect("__name__ type must be str or None");
-            s.to_string_lossy().chars().filter(|x| *x == '.').count()
-        } + 1;
+            Some(s.to_string_lossy().into_owned())
+        }
+    }
+
+    // ugly, but for some reason other attributes/functions for introspection didn't work
+    //
+    // it fails if someone changes __name__, but who would do this?
+    //
+    // maybe i'll fix it later, but anyway it's evaluated only a single time for each callsite
+    pub fn module(&self) -> String {
+        let path_length = self
+            .module_name()
+            .map_or(0, |name| name.chars().filter(|x| *x == '.').count())
+            + 1;
 
         let file = self.code.filename();
         let file = file.to_string_lossy().into_owned();
@@ -1,100 +1,261 @@
+pub mod capture;
+pub mod chrome;
+pub mod fields;
+pub mod filter;
+pub mod flame;
 pub mod fmt;
+mod fork;
+#[cfg(target_os = "linux")]
+pub mod journald;
+pub mod logging;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+pub mod python;
+pub mod syslog;
 
-use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyCFunction};
-use tracing_appender::non_blocking::WorkerGuard;
+use std::sync::{Mutex, OnceLock};
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyTypeError},
+    prelude::*,
+    types::PyCFunction,
+};
 use tracing_subscriber::{
-    FmtSubscriber, Layer, Registry, fmt::time::UtcTime, layer::SubscriberExt, registry,
+    FmtSubscriber, Layer, Registry, fmt::time::UtcTime, layer::SubscriberExt, registry, reload,
     util::SubscriberInitExt,
 };
 
 use crate::{
     imports::get_atexit_register,
-    layer::fmt::{FmtLayer, time::formats::YYYY_MM_DD_HH_MM_SS, to_layer::ToDynLayer},
+    layer::{
+        capture::PyCaptureLayer,
+        chrome::PyChromeTraceLayer,
+        filter::ReloadableFilter,
+        flame::PyFlameLayer,
+        fmt::{FmtLayer, time::formats::YYYY_MM_DD_HH_MM_SS, to_layer::ToDynLayer},
+        logging::PyLoggingLayer,
+        python::PyLayer,
+        syslog::PySyslogLayer,
+    },
 };
 
 trait ThreadSafeLayer = Layer<Registry> + Send + Sync;
 
-// todo: accept *args instead of a Sequence (or maybe not)
-#[pyfunction(name = "init")]
-#[pyo3(signature = (layers = None))]
-pub fn py_init(py: Python<'_>, layers: Option<Bound<'_, PyAny>>) -> PyResult<()> {
-    let layers_with_guards = if let Some(layers) = layers {
-        if let Ok(layers) = layers.extract::<Vec<Bound<'_, FmtLayer>>>() {
-            layers
-                .into_iter()
-                .map(|x: Bound<'_, FmtLayer>| x.dyn_layer())
-                .collect::<PyResult<Vec<(_, _)>>>()?
+type LayerStack = Vec<Box<dyn ThreadSafeLayer>>;
+
+// keeps the worker threads and exporters alive, they're flushed and stopped when it's dropped
+pub trait Flush: Send + Sync {
+    fn flush(&self) -> Result<(), String>;
+
+    // the buffered data would be written by both processes otherwise
+    fn before_fork(&self) {
+        let _ = self.flush();
+    }
+
+    // called in both processes, the threads have to be restarted in the child
+    fn after_fork(&self) {}
+}
+
+// the first guard is flushed and dropped first, e.g. the non-blocking worker before the compression
+// of the files it has rotated
+impl<A: Flush, B: Flush> Flush for (A, B) {
+    fn flush(&self) -> Result<(), String> {
+        self.0.flush()?;
+        self.1.flush()
+    }
+
+    fn before_fork(&self) {
+        self.0.before_fork();
+        self.1.before_fork();
+    }
+
+    fn after_fork(&self) {
+        self.1.after_fork();
+        self.0.after_fork();
+    }
+}
+
+type LayerGuard = Box<dyn Flush>;
+
+type LayerWithGuard = (Box<dyn ThreadSafeLayer>, Option<LayerGuard>);
+
+fn to_dyn_layer(layer: &Bound<'_, PyAny>) -> Option<PyResult<LayerWithGuard>> {
+    if let Ok(layer) = layer.cast::<FmtLayer>() {
+        Some(layer.dyn_layer())
+    } else if let Ok(layer) = layer.cast::<PyLayer>() {
+        Some(layer.dyn_layer())
+    } else if let Ok(layer) = layer.cast::<PyCaptureLayer>() {
+        Some(layer.dyn_layer())
+    } else if let Ok(layer) = layer.cast::<PyLoggingLayer>() {
+        Some(layer.dyn_layer())
+    } else if let Ok(layer) = layer.cast::<PyChromeTraceLayer>() {
+        Some(layer.dyn_layer())
+    } else if let Ok(layer) = layer.cast::<PyFlameLayer>() {
+        Some(layer.dyn_layer())
+    } else if let Ok(layer) = layer.cast::<PySyslogLayer>() {
+        Some(layer.dyn_layer())
+    } else {
+        to_dyn_journald_layer(layer).or_else(|| to_dyn_otlp_layer(layer))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn to_dyn_journald_layer(layer: &Bound<'_, PyAny>) -> Option<PyResult<LayerWithGuard>> {
+    if let Ok(layer) = layer.cast::<journald::PyJournaldLayer>() {
+        Some(layer.dyn_layer())
+    } else {
+        None
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn to_dyn_journald_layer(_layer: &Bound<'_, PyAny>) -> Option<PyResult<LayerWithGuard>> {
+    None
+}
+
+#[cfg(feature = "otlp")]
+fn to_dyn_otlp_layer(layer: &Bound<'_, PyAny>) -> Option<PyResult<LayerWithGuard>> {
+    if let Ok(layer) = layer.cast::<otlp::PyOtlpLayer>() {
+        Some(layer.dyn_layer())
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "otlp"))]
+fn to_dyn_otlp_layer(_layer: &Bound<'_, PyAny>) -> Option<PyResult<LayerWithGuard>> {
+    None
+}
+
+// the subscriber is installed once, re-initialization replaces the layers;
+// the spans that are already open stay in the registry, so the new layers may see them too
+static SUBSCRIBER: OnceLock<reload::Handle<LayerStack, Registry>> = OnceLock::new();
+
+static GUARDS: Mutex<Vec<LayerGuard>> = Mutex::new(vec![]);
+
+fn to_layer_stack(layers: Option<Bound<'_, PyAny>>) -> PyResult<Vec<LayerWithGuard>> {
+    Ok(if let Some(layers) = layers {
+        if let Some(layer) = to_dyn_layer(&layers) {
+            vec![layer?]
         } else {
-            vec![layers.cast::<FmtLayer>()?.dyn_layer()?]
+            layers
+                .try_iter()?
+                .map(|x| {
+                    let x = x?;
+                    to_dyn_layer(&x).unwrap_or_else(|| {
+                        Err(PyTypeError::new_err(format!(
+                            "expected a layer, got {}",
+                            x.get_type()
+                        )))
+                    })
+                })
+                .collect::<PyResult<Vec<_>>>()?
         }
     } else {
         // not using non-blocking logger by default, as it may drop some logs due to 1-second timeout
+        let filter = ReloadableFilter::new(FmtSubscriber::DEFAULT_MAX_LEVEL, None);
         let layer = tracing_subscriber::fmt::layer()
             .with_timer(UtcTime::new(YYYY_MM_DD_HH_MM_SS))
-            .with_filter(FmtSubscriber::DEFAULT_MAX_LEVEL);
+            .with_filter(filter.layer()?);
         let dyn_layer: Box<dyn ThreadSafeLayer> = Box::new(layer);
 
         vec![(dyn_layer, None)]
-    };
+    })
+}
 
-    let (layers, guards): (Vec<_>, Vec<_>) = layers_with_guards.into_iter().unzip();
+// todo: accept *args instead of a Sequence (or maybe not)
+#[pyfunction(name = "init")]
+#[pyo3(signature = (layers = None))]
+pub fn py_init(py: Python<'_>, layers: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+    // the new layers add their filters while they're built
+    let installed = filter::take_installed();
+    let result = init(py, layers);
+    if result.is_err() {
+        filter::restore_installed(installed);
+    }
+    result
+}
 
-    registry()
-        .with(layers)
-        .try_init()
-        .map_err(|x| PyRuntimeError::new_err(x.to_string()))?;
+fn init(py: Python<'_>, layers: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+    let (mut layers, guards): (LayerStack, Vec<_>) = to_layer_stack(layers)?.into_iter().unzip();
+    let guards = guards.into_iter().flatten().collect::<Vec<_>>();
 
-    let guard_vec = PyWorkerGuardVec::new(guards.into_iter().flatten().collect());
-    guard_vec.into_pyobject(py)?.drop_at_exit()
-}
+    let old_guards = if let Some(handle) = SUBSCRIBER.get() {
+        // the per-layer filters are registered when the subscriber is built, the reloaded ones
+        // can't be registered in the installed registry (it's behind the dispatcher, and only a &mut
+        // registry can register them), so they get their ids from a new one: 0, 1, 2..., the same ids
+        // the first init() gave to its layers. it's fine because the ids are only bit positions
+        // in the per-event and per-span filter maps, the registry doesn't store anything else for them,
+        // and the old layers are replaced all at once, so two live filters never share an id.
+        // registering in the installed one would also run out of the 64 ids after a few re-inits
+        layers.on_layer(&mut Registry::default());
 
-#[pyclass]
-struct PyWorkerGuardVec {
-    guards: Option<Vec<WorkerGuard>>,
+        // the layers may need the gil while holding the reload lock
+        py.detach(|| handle.reload(layers))
+            .map_err(|x| PyRuntimeError::new_err(x.to_string()))?;
+        std::mem::replace(&mut *GUARDS.lock().unwrap(), guards)
+    } else {
+        let (layer, handle) = reload::Layer::new(layers);
+        registry()
+            .with(layer)
+            .try_init()
+            .map_err(|x| PyRuntimeError::new_err(x.to_string()))?;
+        let _ = SUBSCRIBER.set(handle);
+        *GUARDS.lock().unwrap() = guards;
+        drop_guards_at_exit(py)?;
+        #[cfg(unix)]
+        fork::register_hooks(py)?;
+        vec![]
+    };
+
+    // the old layers are already replaced, so nothing is written to the stopped workers
+    py.detach(|| drop(old_guards));
+    Ok(())
 }
 
-impl PyWorkerGuardVec {
-    fn new(guards: Vec<WorkerGuard>) -> Self {
-        Self {
-            guards: Some(guards),
-        }
+// blocks until the non-blocking writers have written everything and the exporters have exported everything
+#[pyfunction(name = "flush")]
+pub fn py_flush(py: Python<'_>) -> PyResult<()> {
+    let errors = py.detach(|| {
+        GUARDS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|guard| guard.flush().err())
+            .collect::<Vec<_>>()
+    });
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(PyRuntimeError::new_err(format!(
+            "failed to flush: {}",
+            errors.join(", ")
+        )))
     }
+}
 
-    fn drop_guards(&mut self) {
-        assert!(self.guards.is_some());
-        self.guards = None;
-    }
+// removes all layers and stops the workers and exporters, init() may be called again afterwards
+#[pyfunction(name = "shutdown")]
+pub fn py_shutdown(py: Python<'_>) -> PyResult<()> {
+    let Some(handle) = SUBSCRIBER.get() else {
+        return Ok(());
+    };
+    py.detach(|| handle.reload(LayerStack::new()))
+        .map_err(|x| PyRuntimeError::new_err(x.to_string()))?;
+    filter::take_installed();
+    drop_guards(py);
+    Ok(())
 }
 
-trait PyGuardsMethods {
-    fn drop_at_exit(self) -> PyResult<()>;
+fn drop_guards(py: Python<'_>) {
+    let guards = std::mem::take(&mut *GUARDS.lock().unwrap());
+    py.detach(|| drop(guards));
 }
 
-impl PyGuardsMethods for Bound<'_, PyWorkerGuardVec> {
-    // todo: we can check if it's empty, and skip atexit setup if it is
-    fn drop_at_exit(self) -> PyResult<()> {
-        let closure = match PyCFunction::new_closure(self.py(), None, None, {
-            let guard_vec = self.clone().unbind();
-
-            move |args, _| {
-                let py = args.py();
-                guard_vec.borrow_mut(py).drop_guards();
-            }
-        }) {
-            Ok(closure) => closure,
-            Err(err) => {
-                // python should do it itself, but why not be even more sure that worker threads will be stopped?
-                self.borrow_mut().drop_guards();
-                return Err(err);
-            }
-        };
-        match get_atexit_register(self.py()).call1((&closure,)) {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                // same
-                self.borrow_mut().drop_guards();
-                Err(err)
-            }
-        }
-    }
+// same as shutdown(), the layers are removed before the workers are stopped,
+// so nothing logged afterwards (e.g. by other atexit handlers) is sent to a stopped worker
+fn drop_guards_at_exit(py: Python<'_>) -> PyResult<()> {
+    let closure = PyCFunction::new_closure(py, None, None, |args, _| py_shutdown(args.py()))?;
+    get_atexit_register(py).call1((&closure,))?;
+    Ok(())
 }
@@ -0,0 +1,365 @@
+use std::cell::RefCell;
+
+use pyo3::{
+    exceptions::{PyAssertionError, PyRuntimeError},
+    prelude::*,
+    types::{PyDict, PyList, PyTuple, PyType},
+};
+use tracing::{Dispatch, Event, Level, dispatcher::DefaultGuard, span};
+use tracing_core::LevelFilter;
+use tracing_subscriber::{
+    Layer, Registry,
+    filter::Filtered,
+    layer::{Context, SubscriberExt},
+    registry::Scope,
+};
+
+use crate::{
+    layer::{
+        LayerWithGuard,
+        filter::{LayerFilter, PyEnvFilter},
+        fmt::to_layer::ToDynLayer,
+        python::{fields::PyFieldVisitor, record::PySpan},
+    },
+    level::PyLevel,
+};
+
+thread_local! {
+    // DefaultGuard is !Send, so the guards of the active `with CaptureLayer()` blocks are kept here,
+    // with the address of the layer that entered them
+    static SCOPED: RefCell<Vec<(usize, DefaultGuard)>> = const { RefCell::new(vec![]) };
+}
+
+#[pyclass(name = "CapturedRecord", frozen, get_all)]
+pub struct PyCapturedRecord {
+    // "event", "new_span", "enter", "exit" or "close"
+    kind: &'static str,
+    level: PyLevel,
+    name: &'static str,
+    target: &'static str,
+    message: Option<Py<PyAny>>,
+    // everything except the message, for span records these are the span fields
+    fields: Py<PyDict>,
+    // outermost span first, for span records the span itself is the last one
+    spans: Py<PyTuple>,
+}
+
+impl PyCapturedRecord {
+    fn matches(
+        &self,
+        py: Python<'_>,
+        level: Option<PyLevel>,
+        message: Option<&Bound<'_, PyAny>>,
+        target: Option<&str>,
+        fields: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<bool> {
+        if self.kind != "event"
+            || level.is_some_and(|level| level != self.level)
+            || target.is_some_and(|target| target != self.target)
+        {
+            return Ok(false);
+        }
+
+        if let Some(message) = message {
+            match &self.message {
+                Some(own) if own.bind(py).eq(message)? => {}
+                _ => return Ok(false),
+            }
+        }
+
+        if let Some(fields) = fields {
+            let own = self.fields.bind(py);
+            for (key, value) in fields {
+                match own.get_item(key)? {
+                    Some(own) if own.eq(value)? => {}
+                    _ => return Ok(false),
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[pymethods]
+impl PyCapturedRecord {
+    fn __repr__(&self, py: Python<'_>) -> String {
+        let message = self
+            .message
+            .as_ref()
+            .map_or_else(|| "None".to_owned(), |x| x.bind(py).to_string());
+        format!(
+            "CapturedRecord(kind={:?}, level={:?}, target={:?}, message={:?}, fields={})",
+            self.kind,
+            Level::from(self.level),
+            self.target,
+            message,
+            self.fields.bind(py)
+        )
+    }
+}
+
+// not using a reloadable filter, as capture layers are usually short-lived and
+// shouldn't be affected by tracing.set_level()
+#[pyclass(name = "CaptureLayer", frozen)]
+pub struct PyCaptureLayer {
+    level: LevelFilter,
+    filter: Option<PyEnvFilter>,
+    records: Py<PyList>,
+}
+
+impl PyCaptureLayer {
+    fn layer(&self, py: Python<'_>) -> PyResult<Filtered<CaptureLayer, LayerFilter, Registry>> {
+        Ok(CaptureLayer {
+            records: self.records.clone_ref(py),
+        }
+        .with_filter(PyEnvFilter::layer_filter(self.filter.as_ref(), self.level)?))
+    }
+
+    fn find_records<'py>(
+        &self,
+        py: Python<'py>,
+        level: Option<PyLevel>,
+        message: Option<&Bound<'py, PyAny>>,
+        target: Option<&str>,
+        fields: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Vec<Bound<'py, PyCapturedRecord>>> {
+        let mut found = vec![];
+        for record in self.records.bind(py) {
+            let record = record.cast_into::<PyCapturedRecord>()?;
+            if record.get().matches(py, level, message, target, fields)? {
+                found.push(record);
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[pymethods]
+impl PyCaptureLayer {
+    #[new]
+    #[pyo3(signature = (*, log_level = PyLevel::Trace, filter = None))]
+    fn new(py: Python<'_>, log_level: PyLevel, filter: Option<PyEnvFilter>) -> Self {
+        Self {
+            level: Level::from(log_level).into(),
+            filter,
+            records: PyList::empty(py).unbind(),
+        }
+    }
+
+    // the list itself, not a copy, so it may be cleared or inspected directly
+    #[getter]
+    fn records(&self, py: Python<'_>) -> Py<PyList> {
+        self.records.clone_ref(py)
+    }
+
+    #[getter]
+    fn events<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyCapturedRecord>>> {
+        self.find_records(py, None, None, None, None)
+    }
+
+    fn clear(&self, py: Python<'_>) -> PyResult<()> {
+        self.records.bind(py).del_slice(0, usize::MAX)
+    }
+
+    #[pyo3(signature = (level = None, message = None, target = None, **fields))]
+    fn find<'py>(
+        &self,
+        py: Python<'py>,
+        level: Option<PyLevel>,
+        message: Option<&Bound<'py, PyAny>>,
+        target: Option<&str>,
+        fields: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Vec<Bound<'py, PyCapturedRecord>>> {
+        self.find_records(py, level, message, target, fields)
+    }
+
+    // returns the first matching event, so its other fields can be checked too
+    #[pyo3(signature = (level = None, message = None, target = None, **fields))]
+    fn assert_logged<'py>(
+        &self,
+        py: Python<'py>,
+        level: Option<PyLevel>,
+        message: Option<&Bound<'py, PyAny>>,
+        target: Option<&str>,
+        fields: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyCapturedRecord>> {
+        match self
+            .find_records(py, level, message, target, fields)?
+            .into_iter()
+            .next()
+        {
+            Some(record) => Ok(record),
+            None => Err(PyAssertionError::new_err(format!(
+                "no matching event was logged, captured events: {}",
+                PyList::new(py, self.events(py)?)?
+            ))),
+        }
+    }
+
+    #[pyo3(signature = (level = None, message = None, target = None, **fields))]
+    fn assert_not_logged<'py>(
+        &self,
+        py: Python<'py>,
+        level: Option<PyLevel>,
+        message: Option<&Bound<'py, PyAny>>,
+        target: Option<&str>,
+        fields: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<()> {
+        let found = self.find_records(py, level, message, target, fields)?;
+        if found.is_empty() {
+            Ok(())
+        } else {
+            Err(PyAssertionError::new_err(format!(
+                "expected no matching events, found: {}",
+                PyList::new(py, found)?
+            )))
+        }
+    }
+
+    // captures the events of the current thread only, even if tracing.init() was called;
+    // the global layers don't receive these events while the block is active
+    fn __enter__(slf: Bound<'_, Self>) -> PyResult<Bound<'_, Self>> {
+        let dispatch = Dispatch::new(Registry::default().with(slf.get().layer(slf.py())?));
+        let guard = tracing::dispatcher::set_default(&dispatch);
+        SCOPED.with_borrow_mut(|scoped| scoped.push((slf.as_ptr() as usize, guard)));
+        Ok(slf)
+    }
+
+    // the guards restore the previous dispatcher, so they have to be dropped in reverse order
+    fn __exit__(
+        slf: &Bound<'_, Self>,
+        _exc_type: Option<&Bound<'_, PyType>>,
+        _exc_value: Option<&Bound<'_, PyAny>>,
+        _traceback: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<()> {
+        let owner = slf.as_ptr() as usize;
+        let guard = SCOPED.with_borrow_mut(|scoped| match scoped.last() {
+            Some((last, _)) if *last == owner => Ok(scoped.pop()),
+            _ if scoped.iter().any(|(x, _)| *x == owner) => Err(PyRuntimeError::new_err(
+                "the capture layers must be exited in the reverse order they were entered",
+            )),
+            _ => Err(PyRuntimeError::new_err(
+                "the capture layer is not active on this thread",
+            )),
+        })?;
+        drop(guard);
+        // callsites enabled only by the capture layer would stay enabled otherwise
+        tracing_core::callsite::rebuild_interest_cache();
+        Ok(())
+    }
+}
+
+impl ToDynLayer for Bound<'_, PyCaptureLayer> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
+        Ok((Box::new(self.get().layer(self.py())?), None))
+    }
+}
+
+struct CaptureLayer {
+    records: Py<PyList>,
+}
+
+impl CaptureLayer {
+    fn push(&self, py: Python<'_>, record: PyResult<PyCapturedRecord>) {
+        if let Err(err) = record.and_then(|record| self.records.bind(py).append(record)) {
+            err.write_unraisable(py, None);
+        }
+    }
+
+    fn span_chain<'a>(py: Python<'_>, scope: Option<Scope<'a, Registry>>) -> PyResult<Py<PyTuple>> {
+        let spans = scope
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+            .filter_map(|span| PySpan::from_extensions(py, &span))
+            .collect::<Vec<_>>();
+        Ok(PyTuple::new(py, spans)?.unbind())
+    }
+
+    fn event_record(
+        py: Python<'_>,
+        event: &Event<'_>,
+        ctx: &Context<'_, Registry>,
+    ) -> PyResult<PyCapturedRecord> {
+        let meta = event.metadata();
+
+        let mut visitor = PyFieldVisitor::new(py);
+        event.record(&mut visitor);
+        let fields = visitor.finish()?;
+
+        let message = fields.get_item("message")?;
+        if message.is_some() {
+            fields.del_item("message")?;
+        }
+
+        Ok(PyCapturedRecord {
+            kind: "event",
+            level: PyLevel::from(*meta.level()),
+            name: meta.name(),
+            target: meta.target(),
+            message: message.map(Bound::unbind),
+            fields: fields.unbind(),
+            spans: Self::span_chain(py, ctx.event_scope(event))?,
+        })
+    }
+
+    fn span_record(
+        py: Python<'_>,
+        kind: &'static str,
+        id: &span::Id,
+        ctx: &Context<'_, Registry>,
+    ) -> Option<PyResult<PyCapturedRecord>> {
+        let span = ctx.span(id)?;
+        let py_span = PySpan::from_extensions(py, &span)?;
+        let py_span = py_span.get();
+
+        Some(
+            Self::span_chain(py, Some(span.scope())).map(|spans| PyCapturedRecord {
+                kind,
+                level: py_span.level,
+                name: py_span.name,
+                target: py_span.target,
+                message: None,
+                fields: py_span.fields.clone_ref(py),
+                spans,
+            }),
+        )
+    }
+
+    fn on_span(&self, kind: &'static str, id: &span::Id, ctx: &Context<'_, Registry>) {
+        Python::attach(|py| {
+            if let Some(record) = Self::span_record(py, kind, id, ctx) {
+                self.push(py, record);
+            }
+        });
+    }
+}
+
+impl Layer<Registry> for CaptureLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, Registry>) {
+        Python::attach(|py| match PySpan::get_or_create(py, attrs, id, &ctx) {
+            Ok(_) => {
+                if let Some(record) = Self::span_record(py, "new_span", id, &ctx) {
+                    self.push(py, record);
+                }
+            }
+            Err(err) => err.write_unraisable(py, None),
+        });
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        Python::attach(|py| self.push(py, Self::event_record(py, event, &ctx)));
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, Registry>) {
+        self.on_span("enter", id, &ctx);
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, Registry>) {
+        self.on_span("exit", id, &ctx);
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, Registry>) {
+        self.on_span("close", &id, &ctx);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    fs::File,
+    io::{BufWriter, Write},
+    process,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::Instant,
+};
+
+use pyo3::prelude::*;
+use serde_json::{Map, Value as Json, json};
+use tracing::{Event, span};
+use tracing_subscriber::{Layer, Registry, layer::Context};
+
+use crate::{
+    layer::{
+        Flush, LayerGuard, LayerWithGuard,
+        filter::{PyEnvFilter, ReloadableFilter, reloadable_filter_methods},
+        fmt::{json::json_fields, to_layer::ToDynLayer},
+        fork::ForkSafeMutex,
+    },
+    level::PyLevel,
+};
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // small sequential ids are easier to read in the trace viewer than the os thread ids
+    static THREAD_ID: Cell<u64> = Cell::new(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+}
+
+// writes the spans in Chrome Trace Event Format, which can be opened in Perfetto or chrome://tracing
+#[pyclass(name = "ChromeTraceLayer")]
+pub struct PyChromeTraceLayer {
+    path: String,
+    include_events: bool,
+    filter: ReloadableFilter,
+}
+
+#[pymethods]
+impl PyChromeTraceLayer {
+    #[new]
+    #[pyo3(signature = (path, *, log_level = PyLevel::Info, filter = None, include_events = true))]
+    fn new(
+        path: String,
+        log_level: PyLevel,
+        filter: Option<PyEnvFilter>,
+        include_events: bool,
+    ) -> Self {
+        Self {
+            path,
+            include_events,
+            filter: ReloadableFilter::from_level(log_level, filter),
+        }
+    }
+}
+
+reloadable_filter_methods!(PyChromeTraceLayer, filter);
+
+impl ToDynLayer for Bound<'_, PyChromeTraceLayer> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
+        let this = self.borrow();
+
+        let writer = Arc::new(ForkSafeMutex::new(TraceWriter::new(File::create(
+            &this.path,
+        )?)));
+        let layer = ChromeTraceLayer {
+            writer: writer.clone(),
+            start: Instant::now(),
+            include_events: this.include_events,
+        };
+
+        let guard: LayerGuard = Box::new(TraceWriterGuard(writer));
+        Ok((
+            Box::new(layer.with_filter(this.filter.layer()?)),
+            Some(guard),
+        ))
+    }
+}
+
+struct TraceWriter {
+    out: BufWriter<File>,
+    empty: bool,
+    named_threads: HashSet<u64>,
+}
+
+impl TraceWriter {
+    fn new(file: File) -> Self {
+        let mut out = BufWriter::new(file);
+        // the viewers accept an unterminated array, so the trace is readable even if the process crashes
+        let _ = out.write_all(b"[");
+        Self {
+            out,
+            empty: true,
+            named_threads: HashSet::new(),
+        }
+    }
+
+    // io errors are ignored, there's nowhere to report them
+    fn write(&mut self, event: &Json) {
+        let separator: &[u8] = if self.empty { b"\n" } else { b",\n" };
+        self.empty = false;
+        let _ = self.out.write_all(separator);
+        let _ = serde_json::to_writer(&mut self.out, event);
+    }
+
+    fn thread_id(&mut self) -> u64 {
+        let tid = THREAD_ID.get();
+        if self.named_threads.insert(tid) {
+            let thread = thread::current();
+            let name = thread.name().unwrap_or("unnamed");
+            self.write(&json!({
+                "ph": "M",
+                "name": "thread_name",
+                "pid": process::id(),
+                "tid": tid,
+                "args": { "name": name },
+            }));
+        }
+        tid
+    }
+
+    fn finish(&mut self) {
+        let _ = self.out.write_all(b"\n]\n");
+        let _ = self.out.flush();
+    }
+}
+
+struct TraceWriterGuard(Arc<ForkSafeMutex<TraceWriter>>);
+
+impl Flush for TraceWriterGuard {
+    // the array stays unterminated until the guard is dropped
+    fn flush(&self) -> Result<(), String> {
+        self.0.lock().out.flush().map_err(|e| e.to_string())
+    }
+}
+
+impl Drop for TraceWriterGuard {
+    fn drop(&mut self) {
+        self.0.lock().finish();
+    }
+}
+
+// span fields, recorded once when the span is created and attached to each begin event
+struct SpanArgs(Map<String, Json>);
+
+struct ChromeTraceLayer {
+    writer: Arc<ForkSafeMutex<TraceWriter>>,
+    start: Instant,
+    include_events: bool,
+}
+
+impl ChromeTraceLayer {
+    fn timestamp(&self) -> f64 {
+        // microseconds
+        self.start.elapsed().as_nanos() as f64 / 1000.0
+    }
+
+    fn span_event(&self, phase: &str, id: &span::Id, ctx: &Context<'_, Registry>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let ts = self.timestamp();
+        let meta = span.metadata();
+
+        let mut event = json!({
+            "ph": phase,
+            "name": meta.name(),
+            "cat": meta.target(),
+            "ts": ts,
+            "pid": process::id(),
+        });
+        // the end event matches the last begin event on the same thread, it doesn't need the args
+        if phase == "B"
+            && let Some(args) = span.extensions().get::<SpanArgs>()
+        {
+            event["args"] = Json::Object(args.0.clone());
+        }
+
+        let mut writer = self.writer.lock();
+        event["tid"] = writer.thread_id().into();
+        writer.write(&event);
+    }
+}
+
+impl Layer<Registry> for ChromeTraceLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, Registry>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        // there may be multiple chrome layers, the args are the same for all of them
+        let mut extensions = span.extensions_mut();
+        if extensions.get_mut::<SpanArgs>().is_none() {
+            extensions.insert(SpanArgs(json_fields(attrs)));
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, Registry>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(args) = span.extensions_mut().get_mut::<SpanArgs>()
+        {
+            args.0.extend(json_fields(values));
+        }
+    }
+
+    // generators and coroutines enter and exit their spans each time they're resumed,
+    // so the suspended ones don't take up any time on the thread track
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, Registry>) {
+        self.span_event("B", id, &ctx);
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, Registry>) {
+        self.span_event("E", id, &ctx);
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, Registry>) {
+        if !self.include_events {
+            return;
+        }
+
+        let ts = self.timestamp();
+        let meta = event.metadata();
+
+        let args = json_fields(event);
+        let name = match args.get("message") {
+            Some(Json::String(message)) => message.clone(),
+            _ => meta.name().to_owned(),
+        };
+
+        let mut writer = self.writer.lock();
+        let tid = writer.thread_id();
+        writer.write(&json!({
+            "ph": "i",
+            // thread scope, the event is drawn on the thread track
+            "s": "t",
+            "name": name,
+            "cat": meta.target(),
+            "ts": ts,
+            "pid": process::id(),
+            "tid": tid,
+            "args": args,
+        }));
+    }
+}
@@ -0,0 +1,40 @@
+use std::fmt::Debug;
+
+use tracing::field::{Field, Visit};
+use valuable::Value;
+
+// event fields as text, for the layers that write their own formats
+#[derive(Default)]
+pub struct TextVisitor {
+    pub message: Option<String>,
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl TextVisitor {
+    fn push(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.fields.push((field.name(), value));
+        }
+    }
+}
+
+impl Visit for TextVisitor {
+    fn record_value(&mut self, field: &Field, value: Value<'_>) {
+        let value = match value {
+            Value::String(str) | Value::UnquotedString(str) => str.to_owned(),
+            Value::Unit => "None".to_owned(),
+            other => format!("{other:?}"),
+        };
+        self.push(field, value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.push(field, format!("{value:?}"));
+    }
+}
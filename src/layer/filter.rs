@@ -0,0 +1,341 @@
+#[cfg(unix)]
+use std::any::Any;
+use std::{
+    env,
+    sync::{Arc, Mutex},
+};
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyTypeError, PyValueError},
+    prelude::*,
+};
+use tracing::{Level, Metadata, Subscriber, span};
+use tracing_core::{Interest, LevelFilter};
+use tracing_subscriber::{
+    EnvFilter, Registry,
+    layer::{Context, Filter},
+    registry::LookupSpan,
+    reload,
+};
+
+use crate::{layer::fork::ForkSafeMutex, level::PyLevel};
+
+pub const DEFAULT_ENV_VAR: &str = "TRACING_PY_LOG";
+
+pub type ReloadLayerFilter = reload::Layer<LayerFilter, Registry>;
+
+// layers installed by init(), so tracing.set_level()/set_filter() can reach all of them
+static INSTALLED: Mutex<Vec<ReloadableFilter>> = Mutex::new(vec![]);
+
+// init() and shutdown() replace the whole stack, the filters of the removed layers aren't reachable anymore
+pub fn take_installed() -> Vec<ReloadableFilter> {
+    std::mem::take(&mut *INSTALLED.lock().unwrap())
+}
+
+// when init() fails, the old stack stays installed
+pub fn restore_installed(filters: Vec<ReloadableFilter>) {
+    *INSTALLED.lock().unwrap() = filters;
+}
+
+// taken by the thread that forks, the filters may call python code (the layers' callsite hooks) while it's held
+#[cfg(unix)]
+pub fn try_lock_for_fork() -> Option<impl Any> {
+    INSTALLED.try_lock().ok()
+}
+
+pub enum LayerFilter {
+    Level(LevelFilter),
+    Env(Box<EnvFilter>),
+    // the events and spans more severe than the level are skipped too
+    Capped(Box<LayerFilter>, Level),
+}
+
+// EnvFilter is neither Clone nor Copy, so only the directives are stored, and a new filter is built for each layer
+#[pyclass(name = "EnvFilter", skip_from_py_object)]
+#[derive(Clone)]
+pub struct PyEnvFilter {
+    directives: String,
+}
+
+impl PyEnvFilter {
+    // with_default_directive() is only used when there are no directives at all, so the level is prepended
+    // instead; it's the directive for the targets that aren't matched, unless there's a bare level already
+    fn build(&self, default: LevelFilter) -> PyResult<EnvFilter> {
+        EnvFilter::builder()
+            .parse(format!("{default},{}", self.directives))
+            .map_err(|e| PyValueError::new_err(format!("invalid filter directives: {e}")))
+    }
+
+    pub fn layer_filter(filter: Option<&Self>, default: LevelFilter) -> PyResult<LayerFilter> {
+        Ok(match filter {
+            Some(filter) => LayerFilter::Env(Box::new(filter.build(default)?)),
+            None => LayerFilter::Level(default),
+        })
+    }
+}
+
+#[pymethods]
+impl PyEnvFilter {
+    #[new]
+    pub fn new(directives: String) -> PyResult<Self> {
+        let filter = Self { directives };
+        // parsing it once to raise an error early, the level doesn't matter here
+        filter.build(LevelFilter::TRACE)?;
+        Ok(filter)
+    }
+
+    // the variable is read when the filter is created, not when the layer is initialized
+    #[staticmethod]
+    #[pyo3(signature = (var = DEFAULT_ENV_VAR.to_owned()))]
+    fn from_env(var: String) -> PyResult<Self> {
+        Self::new(env::var(var).unwrap_or_default())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("EnvFilter({:?})", self.directives)
+    }
+}
+
+impl<'a, 'py> FromPyObject<'a, 'py> for PyEnvFilter {
+    type Error = PyErr;
+
+    fn extract(obj: pyo3::Borrowed<'a, 'py, PyAny>) -> Result<Self, Self::Error> {
+        if let Ok(filter) = obj.cast::<PyEnvFilter>() {
+            Ok(filter.borrow().clone())
+        } else if let Ok(directives) = obj.extract::<String>() {
+            Self::new(directives)
+        } else {
+            Err(PyTypeError::new_err("expected an EnvFilter or a string"))
+        }
+    }
+}
+
+// reload::Layer needs a concrete filter type, so the level and env filters are merged into a single enum
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Filter<S> for LayerFilter {
+    fn enabled(&self, meta: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        match self {
+            Self::Level(level) => Filter::<S>::enabled(level, meta, cx),
+            Self::Env(env) => Filter::<S>::enabled(&**env, meta, cx),
+            // tracing's levels are ordered by verbosity, ERROR is the least one
+            Self::Capped(filter, max_level) => {
+                meta.level() >= max_level && Filter::<S>::enabled(&**filter, meta, cx)
+            }
+        }
+    }
+
+    fn callsite_enabled(&self, meta: &'static Metadata<'static>) -> Interest {
+        match self {
+            Self::Level(level) => Filter::<S>::callsite_enabled(level, meta),
+            Self::Env(env) => Filter::<S>::callsite_enabled(&**env, meta),
+            Self::Capped(_, max_level) if meta.level() < max_level => Interest::never(),
+            Self::Capped(filter, _) => Filter::<S>::callsite_enabled(&**filter, meta),
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        match self {
+            Self::Level(level) => Filter::<S>::max_level_hint(level),
+            Self::Env(env) => Filter::<S>::max_level_hint(&**env),
+            Self::Capped(filter, _) => Filter::<S>::max_level_hint(&**filter),
+        }
+    }
+
+    // LevelFilter doesn't care about spans, but EnvFilter may have span directives
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        match self {
+            Self::Level(_) => {}
+            Self::Env(env) => Filter::<S>::on_new_span(&**env, attrs, id, ctx),
+            Self::Capped(filter, _) => Filter::<S>::on_new_span(&**filter, attrs, id, ctx),
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        match self {
+            Self::Level(_) => {}
+            Self::Env(env) => Filter::<S>::on_record(&**env, id, values, ctx),
+            Self::Capped(filter, _) => Filter::<S>::on_record(&**filter, id, values, ctx),
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        match self {
+            Self::Level(_) => {}
+            Self::Env(env) => Filter::<S>::on_enter(&**env, id, ctx),
+            Self::Capped(filter, _) => Filter::<S>::on_enter(&**filter, id, ctx),
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        match self {
+            Self::Level(_) => {}
+            Self::Env(env) => Filter::<S>::on_exit(&**env, id, ctx),
+            Self::Capped(filter, _) => Filter::<S>::on_exit(&**filter, id, ctx),
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        match self {
+            Self::Level(_) => {}
+            Self::Env(env) => Filter::<S>::on_close(&**env, id, ctx),
+            Self::Capped(filter, _) => Filter::<S>::on_close(&**filter, id, ctx),
+        }
+    }
+}
+
+struct FilterState {
+    level: LevelFilter,
+    // the most severe level that passes, all of them do if it's None
+    max_level: Option<Level>,
+    directives: Option<PyEnvFilter>,
+    handles: Vec<reload::Handle<LayerFilter, Registry>>,
+}
+
+impl FilterState {
+    fn layer_filter(&self) -> PyResult<LayerFilter> {
+        let filter = PyEnvFilter::layer_filter(self.directives.as_ref(), self.level)?;
+        Ok(match self.max_level {
+            Some(max_level) => LayerFilter::Capped(Box::new(filter), max_level),
+            None => filter,
+        })
+    }
+
+    // reload() rebuilds the callsite interest cache itself, so the leaked callsites pick up the new filter
+    fn reload(&mut self) -> PyResult<()> {
+        let mut alive = Vec::with_capacity(self.handles.len());
+        for handle in &self.handles {
+            match handle.reload(self.layer_filter()?) {
+                Ok(()) => alive.push(handle.clone()),
+                // the layer isn't used anymore
+                Err(err) if err.is_dropped() => {}
+                Err(err) => return Err(PyRuntimeError::new_err(err.to_string())),
+            }
+        }
+        self.handles = alive;
+        Ok(())
+    }
+}
+
+// a max_level more verbose than the level would skip everything
+pub fn check_level_range(level: LevelFilter, max_level: Option<Level>) -> PyResult<()> {
+    match max_level {
+        Some(max_level) if max_level > level => Err(PyValueError::new_err(format!(
+            "max_level {max_level} is more verbose than log_level {level}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// shared between the python layer object and the list of installed layers,
+// so the filter can be changed from both places
+#[derive(Clone)]
+pub struct ReloadableFilter(Arc<ForkSafeMutex<FilterState>>);
+
+impl ReloadableFilter {
+    pub fn new(level: LevelFilter, directives: Option<PyEnvFilter>) -> Self {
+        Self::with_max_level(level, None, directives)
+    }
+
+    pub fn from_level(level: PyLevel, directives: Option<PyEnvFilter>) -> Self {
+        Self::new(Level::from(level).into(), directives)
+    }
+
+    pub fn with_max_level(
+        level: LevelFilter,
+        max_level: Option<Level>,
+        directives: Option<PyEnvFilter>,
+    ) -> Self {
+        Self(Arc::new(ForkSafeMutex::new(FilterState {
+            level,
+            max_level,
+            directives,
+            handles: vec![],
+        })))
+    }
+
+    // there's always a filter, even if it filters nothing, as otherwise there'd be nothing to reload
+    pub fn layer(&self) -> PyResult<ReloadLayerFilter> {
+        let layer = {
+            let mut state = self.0.lock();
+            let (layer, handle) = reload::Layer::new(state.layer_filter()?);
+            state.handles.push(handle);
+            layer
+        };
+
+        // the state lock must not be held here, set_level() locks these mutexes in the opposite order
+        let mut installed = INSTALLED.lock().unwrap();
+        if !installed.iter().any(|x| Arc::ptr_eq(&x.0, &self.0)) {
+            installed.push(self.clone());
+        }
+        Ok(layer)
+    }
+
+    pub fn check_level(&self, level: LevelFilter) -> PyResult<()> {
+        check_level_range(level, self.0.lock().max_level)
+    }
+
+    pub fn set_level(&self, level: LevelFilter) -> PyResult<()> {
+        let mut state = self.0.lock();
+        check_level_range(level, state.max_level)?;
+        state.level = level;
+        state.reload()
+    }
+
+    pub fn set_max_level(&self, max_level: Option<Level>) -> PyResult<()> {
+        let mut state = self.0.lock();
+        check_level_range(state.level, max_level)?;
+        state.max_level = max_level;
+        state.reload()
+    }
+
+    pub fn set_filter(&self, directives: Option<PyEnvFilter>) -> PyResult<()> {
+        let mut state = self.0.lock();
+        state.directives = directives;
+        state.reload()
+    }
+}
+
+// changes the level of every layer installed by init(), filter directives are kept;
+// nothing is changed if the level is below the max_level of one of them
+#[pyfunction(name = "set_level")]
+pub fn py_set_level(level: PyLevel) -> PyResult<()> {
+    let level = Level::from(level).into();
+    let installed = INSTALLED.lock().unwrap();
+    for filter in installed.iter() {
+        filter.check_level(level)?;
+    }
+    for filter in installed.iter() {
+        filter.set_level(level)?;
+    }
+    Ok(())
+}
+
+// replaces the filter directives of every layer installed by init()
+#[pyfunction(name = "set_filter")]
+pub fn py_set_filter(filter: Option<PyEnvFilter>) -> PyResult<()> {
+    for installed in INSTALLED.lock().unwrap().iter() {
+        installed.set_filter(filter.clone())?;
+    }
+    Ok(())
+}
+
+// set_level() and set_filter() of the layers that keep their ReloadableFilter in `$field`,
+// they affect the already installed layers too; macro 2.0 hygiene breaks #[pymethods], like in event.rs
+macro_rules! reloadable_filter_methods {
+    ($layer:ty, $field:ident) => {
+        #[::pyo3::pymethods]
+        impl $layer {
+            fn set_level(&self, level: $crate::level::PyLevel) -> ::pyo3::PyResult<()> {
+                self.$field.set_level(::tracing::Level::from(level).into())
+            }
+
+            fn set_filter(
+                &self,
+                filter: Option<$crate::layer::filter::PyEnvFilter>,
+            ) -> ::pyo3::PyResult<()> {
+                self.$field.set_filter(filter)
+            }
+        }
+    };
+}
+
+pub(crate) use reloadable_filter_methods;
@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::{self, ThreadId},
+    time::Instant,
+};
+
+use pyo3::prelude::*;
+use tracing::span;
+use tracing_subscriber::{Layer, Registry, layer::Context};
+
+use crate::{
+    layer::{
+        Flush, LayerGuard, LayerWithGuard,
+        filter::{PyEnvFilter, ReloadableFilter, reloadable_filter_methods},
+        fmt::to_layer::ToDynLayer,
+        fork::ForkSafeMutex,
+    },
+    level::PyLevel,
+};
+
+#[pyclass(
+    name = "FlameMode",
+    rename_all = "SCREAMING_SNAKE_CASE",
+    from_py_object
+)]
+#[derive(Clone, Copy)]
+pub enum PyFlameMode {
+    // time between span creation and closing, including the time coroutines spend suspended
+    WallClock,
+    // only the time the span was entered
+    Busy,
+}
+
+// writes folded stack lines (`root;child;grandchild <nanoseconds>`), which can be rendered by inferno or flamegraph.pl
+#[pyclass(name = "FlameLayer")]
+pub struct PyFlameLayer {
+    path: String,
+    mode: PyFlameMode,
+    filter: ReloadableFilter,
+}
+
+#[pymethods]
+impl PyFlameLayer {
+    #[new]
+    #[pyo3(signature = (path, *, mode = PyFlameMode::Busy, log_level = PyLevel::Info, filter = None))]
+    fn new(
+        path: String,
+        mode: PyFlameMode,
+        log_level: PyLevel,
+        filter: Option<PyEnvFilter>,
+    ) -> Self {
+        Self {
+            path,
+            mode,
+            filter: ReloadableFilter::from_level(log_level, filter),
+        }
+    }
+}
+
+reloadable_filter_methods!(PyFlameLayer, filter);
+
+impl ToDynLayer for Bound<'_, PyFlameLayer> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
+        let this = self.borrow();
+
+        let out = Arc::new(ForkSafeMutex::new(BufWriter::new(File::create(
+            &this.path,
+        )?)));
+        let layer = FlameLayer {
+            id: NEXT_LAYER_ID.fetch_add(1, Ordering::Relaxed),
+            out: out.clone(),
+            mode: this.mode,
+            threads: ForkSafeMutex::default(),
+        };
+
+        let guard: LayerGuard = Box::new(FlushGuard(out));
+        Ok((
+            Box::new(layer.with_filter(this.filter.layer()?)),
+            Some(guard),
+        ))
+    }
+}
+
+struct FlushGuard(Arc<ForkSafeMutex<BufWriter<File>>>);
+
+impl Flush for FlushGuard {
+    fn flush(&self) -> Result<(), String> {
+        self.0.lock().flush().map_err(|e| e.to_string())
+    }
+}
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        let _ = Flush::flush(self);
+    }
+}
+
+// the spans are kept in the extensions until they're closed, and written once;
+// the sample of a span is its own time, the time of its children is subtracted
+struct SpanTiming {
+    created: Instant,
+    busy: u64,
+    children_wall: u64,
+}
+
+// the extensions are shared by all layers, each flame layer keeps its own timing
+#[derive(Default)]
+struct SpanTimings(HashMap<u64, SpanTiming>);
+
+static NEXT_LAYER_ID: AtomicU64 = AtomicU64::new(0);
+
+// entered spans of a single thread, the time since the last enter/exit is attributed to the innermost one
+#[derive(Default)]
+struct ThreadStack {
+    entered: Vec<span::Id>,
+    last: Option<Instant>,
+}
+
+struct FlameLayer {
+    id: u64,
+    out: Arc<ForkSafeMutex<BufWriter<File>>>,
+    mode: PyFlameMode,
+    threads: ForkSafeMutex<HashMap<ThreadId, ThreadStack>>,
+}
+
+impl FlameLayer {
+    // attributes the time since the last enter/exit on this thread to the innermost entered span
+    fn advance(&self, stack: &mut ThreadStack, now: Instant, ctx: &Context<'_, Registry>) {
+        if let (Some(last), Some(innermost)) = (stack.last, stack.entered.last())
+            && let Some(span) = ctx.span(innermost)
+            && let Some(timings) = span.extensions_mut().get_mut::<SpanTimings>()
+            && let Some(timing) = timings.0.get_mut(&self.id)
+        {
+            timing.busy += u64::try_from((now - last).as_nanos()).unwrap_or(u64::MAX);
+        }
+        stack.last = Some(now);
+    }
+}
+
+impl Layer<Registry> for FlameLayer {
+    fn on_new_span(
+        &self,
+        _attrs: &span::Attributes<'_>,
+        id: &span::Id,
+        ctx: Context<'_, Registry>,
+    ) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if extensions.get_mut::<SpanTimings>().is_none() {
+            extensions.insert(SpanTimings::default());
+        }
+        if let Some(timings) = extensions.get_mut::<SpanTimings>() {
+            timings.0.insert(
+                self.id,
+                SpanTiming {
+                    created: Instant::now(),
+                    busy: 0,
+                    children_wall: 0,
+                },
+            );
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, Registry>) {
+        let now = Instant::now();
+        let mut threads = self.threads.lock();
+        let stack = threads.entry(thread::current().id()).or_default();
+        self.advance(stack, now, &ctx);
+        stack.entered.push(id.clone());
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, Registry>) {
+        let now = Instant::now();
+        let mut threads = self.threads.lock();
+        let stack = threads.entry(thread::current().id()).or_default();
+        self.advance(stack, now, &ctx);
+        if let Some(position) = stack.entered.iter().rposition(|x| x == id) {
+            stack.entered.remove(position);
+        }
+        if stack.entered.is_empty() {
+            threads.remove(&thread::current().id());
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, Registry>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span
+            .extensions_mut()
+            .get_mut::<SpanTimings>()
+            .and_then(|timings| timings.0.remove(&self.id))
+        else {
+            return;
+        };
+
+        let wall = u64::try_from(timing.created.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        if let Some(parent) = span.parent()
+            && let Some(timings) = parent.extensions_mut().get_mut::<SpanTimings>()
+            && let Some(parent_timing) = timings.0.get_mut(&self.id)
+        {
+            parent_timing.children_wall += wall;
+        }
+
+        let value = match self.mode {
+            // children of a coroutine may run concurrently, so the sum may be greater than the parent time
+            PyFlameMode::WallClock => wall.saturating_sub(timing.children_wall),
+            PyFlameMode::Busy => timing.busy,
+        };
+        if value == 0 {
+            return;
+        }
+
+        let stack = span
+            .scope()
+            .from_root()
+            .map(|span| span.metadata().target())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let _ = writeln!(self.out.lock(), "{stack} {value}");
+    }
+}
@@ -1,27 +1,50 @@
+pub mod ecs;
+pub mod event;
 pub mod file;
+pub mod gelf;
+pub mod json;
+pub mod line;
+pub mod logfmt;
+pub mod non_blocking;
+pub mod py_file;
+pub mod reopen;
+pub mod rolling;
 pub mod rotation;
+pub mod socket;
 pub mod span;
 pub mod time;
 pub mod to_layer;
+pub mod tree;
 
-use pyo3::prelude::*;
+use std::sync::atomic::Ordering;
+
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+};
 use tracing::Level;
 use tracing_subscriber::fmt::format::FmtSpan;
 
 use crate::{
-    layer::fmt::{
-        file::{LogFile, NonBlocking},
-        span::PyFmtSpan,
-        time::timer::PyTimer,
+    layer::{
+        filter::{PyEnvFilter, ReloadableFilter, check_level_range, reloadable_filter_methods},
+        fmt::{
+            ecs::EcsFormat,
+            file::{LogFile, NonBlocking},
+            json::JsonFormat,
+            line::LineFormat,
+            span::PyFmtSpan,
+            time::timer::PyTimer,
+        },
     },
     level::PyLevel,
 };
 
 #[pyclass]
 pub struct FmtLayer {
-    log_level: Level,
+    filter: ReloadableFilter,
     file: LogFile,
-    format: PyFormat,
+    format: LogFormat,
     fmt_span: FmtSpan,
     non_blocking: Option<NonBlocking>,
     log_internal_errors: Option<bool>,
@@ -44,10 +67,13 @@ impl FmtLayer {
     #[pyo3(
         signature = (*,
         log_level = PyLevel::Info,
+        max_level = None,
+        filter = None,
         // tracing uses stdout by default, not sure why
         // https://github.com/tokio-rs/tracing/issues/2492
         file = LogFile::Stdout,
-        format = PyFormat::Full,
+        format = None,
+        line_format = None,
         fmt_span = Python::attach(|x| {Py::new(x, PyFmtSpan::NONE)}).unwrap(),
         non_blocking = None,
         log_internal_errors = None,
@@ -61,8 +87,11 @@ impl FmtLayer {
     fn new(
         py: Python,
         log_level: PyLevel,
+        max_level: Option<PyLevel>,
+        filter: Option<PyEnvFilter>,
         file: LogFile,
-        format: PyFormat,
+        format: Option<LogFormat>,
+        line_format: Option<&str>,
         fmt_span: Py<PyFmtSpan>,
         non_blocking: Option<NonBlocking>,
         log_internal_errors: Option<bool>,
@@ -73,13 +102,25 @@ impl FmtLayer {
         with_line_number: Option<bool>,
         with_target: Option<bool>,
         with_thread_ids: Option<bool>,
-    ) -> Self {
-        Self {
-            log_level: Level::from(log_level),
+    ) -> PyResult<Self> {
+        // the template replaces the format
+        let format = match (line_format, format) {
+            (Some(_), Some(_)) => {
+                return Err(PyValueError::new_err(
+                    "line_format replaces format, only one of them can be passed",
+                ));
+            }
+            (Some(line_format), None) => LogFormat::Line(LineFormat::parse(line_format)?),
+            (None, format) => format.unwrap_or(LogFormat::Predefined(PyFormat::Full)),
+        };
+        let (level, max_level) = (Level::from(log_level).into(), max_level.map(Level::from));
+        check_level_range(level, max_level)?;
+        Ok(Self {
+            filter: ReloadableFilter::with_max_level(level, max_level, filter),
             file,
             format,
             fmt_span: FmtSpan::from(&*fmt_span.borrow(py)),
-            non_blocking,
+            non_blocking: non_blocking.map(NonBlocking::with_own_counter),
             log_internal_errors,
             timer,
             with_ansi,
@@ -88,10 +129,26 @@ impl FmtLayer {
             with_line_number,
             with_target,
             with_thread_ids,
-        }
+        })
+    }
+
+    // affects the already installed layer, too
+    fn set_max_level(&self, max_level: Option<PyLevel>) -> PyResult<()> {
+        self.filter.set_max_level(max_level.map(Level::from))
+    }
+
+    // lines dropped because the buffer was full, including the writers created by the previous init() calls;
+    // always 0 if it's not lossy
+    #[getter]
+    fn dropped_lines(&self) -> u64 {
+        self.non_blocking.as_ref().map_or(0, |non_blocking| {
+            non_blocking.dropped_lines.load(Ordering::Relaxed)
+        })
     }
 }
 
+reloadable_filter_methods!(FmtLayer, filter);
+
 #[pyclass(name = "Format", rename_all = "UPPERCASE", from_py_object)]
 #[derive(Clone, Copy)]
 pub enum PyFormat {
@@ -99,4 +156,37 @@ pub enum PyFormat {
     Compact,
     Pretty,
     Json,
+    Logfmt,
+    Ecs,
+    Gelf,
+    Tree,
+}
+
+#[derive(Clone)]
+pub enum LogFormat {
+    Predefined(PyFormat),
+    Json(JsonFormat),
+    Ecs(EcsFormat),
+    Line(LineFormat),
+}
+
+impl<'a, 'py> FromPyObject<'a, 'py> for LogFormat {
+    type Error = PyErr;
+
+    fn extract(obj: pyo3::Borrowed<'a, 'py, PyAny>) -> Result<Self, Self::Error> {
+        if let Ok(format) = obj.extract::<PyFormat>() {
+            Ok(match format {
+                PyFormat::Ecs => Self::Ecs(EcsFormat::default()),
+                format => Self::Predefined(format),
+            })
+        } else if let Ok(json) = obj.extract::<JsonFormat>() {
+            Ok(Self::Json(json))
+        } else if let Ok(ecs) = obj.extract::<EcsFormat>() {
+            Ok(Self::Ecs(ecs))
+        } else {
+            Err(PyTypeError::new_err(
+                "expected a Format, a JsonFormat or an EcsFormat",
+            ))
+        }
+    }
 }
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde_json::Value as Json;
+use time::OffsetDateTime;
+use tracing::Event;
+use tracing_subscriber::{
+    Registry,
+    fmt::{FmtContext, FormatFields, format::Writer},
+};
+
+use crate::layer::fmt::{
+    json::{flat_fields, json_line},
+    time::formats::ISO8601,
+};
+
+const ECS_VERSION: &str = "8.11.0";
+
+// Format.ECS with options
+#[pyclass(name = "EcsFormat", frozen, from_py_object)]
+#[derive(Clone)]
+pub struct EcsFormat {
+    namespace: String,
+}
+
+impl Default for EcsFormat {
+    fn default() -> Self {
+        Self {
+            namespace: "labels".to_owned(),
+        }
+    }
+}
+
+#[pymethods]
+impl EcsFormat {
+    #[new]
+    #[pyo3(signature = (*, namespace = "labels".to_owned()))]
+    fn new(namespace: String) -> PyResult<Self> {
+        if namespace.is_empty() {
+            return Err(PyValueError::new_err("namespace must not be empty"));
+        }
+        Ok(Self { namespace })
+    }
+}
+
+// the first three keys are the ones ecs-logging requires, the rest are dotted like in the other ecs loggers
+pub fn format_event<N>(
+    options: &EcsFormat,
+    ctx: &FmtContext<'_, Registry, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+) -> fmt::Result
+where
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let meta = event.metadata();
+    let (message, fields) = flat_fields(ctx, event);
+
+    let mut object: Vec<(&str, Json)> = vec![
+        (
+            "@timestamp",
+            OffsetDateTime::now_utc()
+                .format(&ISO8601)
+                .map_err(|_| fmt::Error)?
+                .into(),
+        ),
+        ("log.level", meta.level().as_str().to_lowercase().into()),
+        ("message", message.unwrap_or_else(|| "".into())),
+        ("ecs.version", ECS_VERSION.into()),
+        ("log.logger", meta.target().into()),
+    ];
+    if let Some(file) = meta.file() {
+        object.push(("log.origin.file.name", file.into()));
+    }
+    if let Some(line) = meta.line() {
+        object.push(("log.origin.file.line", line.into()));
+    }
+    if !fields.is_empty() {
+        object.push((&options.namespace, Json::Object(fields)));
+    }
+
+    writeln!(writer, "{}", json_line(object))
+}
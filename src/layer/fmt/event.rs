@@ -0,0 +1,134 @@
+use std::fmt;
+
+use tracing::Event;
+use tracing_subscriber::{
+    Registry,
+    fmt::{FmtContext, FormatEvent, FormatFields, format::Writer, time::FormatTime},
+};
+
+use crate::layer::fmt::{
+    ecs::{self, EcsFormat},
+    gelf,
+    json::{self, JsonFormat},
+    line::{self, LineFormat},
+    logfmt,
+    tree::{self, TreeContext},
+};
+
+// the formats that tracing doesn't have
+pub enum CustomFormat {
+    Json(JsonFormat),
+    Ecs(EcsFormat),
+    Line(LineFormat),
+    Logfmt,
+    // gelf messages must have a host, it's resolved once
+    Gelf(String),
+    Tree,
+}
+
+// tracing's own formats, or one of the custom ones
+pub enum LayerFormat<T = ()> {
+    Full,
+    Compact,
+    Pretty,
+    Json,
+    Custom(EventFormat<T>),
+}
+
+impl<T> LayerFormat<T> {
+    pub fn with_timer<T2>(self, timer: T2) -> LayerFormat<T2> {
+        match self {
+            Self::Full => LayerFormat::Full,
+            Self::Compact => LayerFormat::Compact,
+            Self::Pretty => LayerFormat::Pretty,
+            Self::Json => LayerFormat::Json,
+            Self::Custom(format) => LayerFormat::Custom(format.with_timer(timer)),
+        }
+    }
+}
+
+// the custom formats get the FmtLayer options and the timer themselves
+pub struct EventFormat<T = ()> {
+    pub format: CustomFormat,
+    // only for Format.TREE
+    pub tree: TreeContext,
+    pub timer: T,
+    // false if timer=None
+    pub has_timer: bool,
+    pub with_level: bool,
+    pub with_target: bool,
+    pub with_file: bool,
+    pub with_line_number: bool,
+    pub with_thread_ids: bool,
+}
+
+impl EventFormat {
+    pub fn new(
+        format: CustomFormat,
+        with_level: Option<bool>,
+        with_target: Option<bool>,
+        with_file: Option<bool>,
+        with_line_number: Option<bool>,
+        with_thread_ids: Option<bool>,
+    ) -> Self {
+        // same defaults as in tracing's formats
+        Self {
+            format,
+            tree: TreeContext::default(),
+            timer: (),
+            has_timer: false,
+            with_level: with_level.unwrap_or(true),
+            with_target: with_target.unwrap_or(true),
+            with_file: with_file.unwrap_or(false),
+            with_line_number: with_line_number.unwrap_or(false),
+            with_thread_ids: with_thread_ids.unwrap_or(false),
+        }
+    }
+}
+
+impl<T> EventFormat<T> {
+    pub fn with_timer<T2>(self, timer: T2) -> EventFormat<T2> {
+        EventFormat {
+            format: self.format,
+            tree: self.tree,
+            timer,
+            has_timer: true,
+            with_level: self.with_level,
+            with_target: self.with_target,
+            with_file: self.with_file,
+            with_line_number: self.with_line_number,
+            with_thread_ids: self.with_thread_ids,
+        }
+    }
+}
+
+impl<T: FormatTime> EventFormat<T> {
+    // None if there's no timer
+    pub fn timestamp(&self) -> Option<String> {
+        let mut ts = String::new();
+        self.timer.format_time(&mut Writer::new(&mut ts)).ok()?;
+        (!ts.is_empty()).then_some(ts)
+    }
+}
+
+impl<T, N> FormatEvent<Registry, N> for EventFormat<T>
+where
+    T: FormatTime,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, Registry, N>,
+        writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        match &self.format {
+            CustomFormat::Json(options) => json::format_event(self, options, ctx, writer, event),
+            CustomFormat::Ecs(options) => ecs::format_event(options, ctx, writer, event),
+            CustomFormat::Line(template) => line::format_event(self, template, ctx, writer, event),
+            CustomFormat::Logfmt => logfmt::format_event(self, ctx, writer, event),
+            CustomFormat::Tree => tree::format_event(self, ctx, writer, event),
+            CustomFormat::Gelf(hostname) => gelf::format_event(hostname, ctx, writer, event),
+        }
+    }
+}
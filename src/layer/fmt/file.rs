@@ -1,12 +1,27 @@
-use pyo3::{exceptions::PyTypeError, prelude::*};
-use tracing_appender::rolling::Rotation;
+use std::{
+    sync::{Arc, atomic::AtomicU64},
+    time::Duration,
+};
+
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    intern,
+    prelude::*,
+};
+
+use crate::layer::fmt::{
+    rotation::{PyCompression, PyRotation},
+    socket::PySocketLog,
+};
 
-use crate::layer::fmt::rotation::PyRotation;
 pub enum LogFile {
     Stdout,
     Stderr,
     Path(String),
     Rolling(PyRollingLog),
+    Socket(PySocketLog),
+    // a file-like object, shared by the writers, so they can be created without the gil
+    Python(Arc<Py<PyAny>>),
 }
 
 impl<'a, 'py> FromPyObject<'a, 'py> for LogFile {
@@ -20,11 +35,15 @@ impl<'a, 'py> FromPyObject<'a, 'py> for LogFile {
             })
         } else if let Ok(rolling) = obj.extract::<PyRollingLog>() {
             Ok(Self::Rolling(rolling))
+        } else if let Ok(socket) = obj.extract::<PySocketLog>() {
+            Ok(Self::Socket(socket))
         } else if let Ok(path) = obj.extract::<String>() {
             Ok(Self::Path(path))
+        } else if obj.hasattr(intern!(obj.py(), "write"))? {
+            Ok(Self::Python(Arc::new(obj.to_owned().unbind())))
         } else {
             Err(PyTypeError::new_err(
-                "expected a File, a RollingLog, or a string",
+                "expected a File, a RollingLog, a SocketLog, a string, or an object with a write() method",
             ))
         }
     }
@@ -41,24 +60,157 @@ pub enum PyLogFile {
 pub struct PyRollingLog {
     pub dir: String,
     pub prefix: String,
-    pub rotation: Rotation,
+    pub rotation: PyRotation,
+    pub suffix: Option<String>,
+    // in bytes
+    pub max_size: Option<u64>,
+    pub max_files: Option<usize>,
+    // the rotated files are compressed in the background
+    pub compression: Option<PyCompression>,
 }
 
 #[pymethods]
 impl PyRollingLog {
     #[new]
-    fn new(dir: String, prefix: String, rotation: PyRotation) -> Self {
-        Self {
+    #[pyo3(signature = (dir, prefix, rotation = PyRotation::Never, *, suffix = None, max_size = None, max_files = None, compression = None))]
+    fn new(
+        dir: String,
+        prefix: String,
+        rotation: PyRotation,
+        suffix: Option<String>,
+        max_size: Option<u64>,
+        max_files: Option<usize>,
+        compression: Option<PyCompression>,
+    ) -> PyResult<Self> {
+        if cfg!(not(feature = "compression")) && compression.is_some() {
+            return Err(PyValueError::new_err(
+                "tracing was built without the compression feature",
+            ));
+        }
+        if max_size == Some(0) {
+            return Err(PyValueError::new_err("max_size must be greater than 0"));
+        }
+        if max_files == Some(0) {
+            return Err(PyValueError::new_err("max_files must be greater than 0"));
+        }
+        Ok(Self {
             dir,
             prefix,
-            rotation: rotation.into(),
+            rotation,
+            suffix,
+            max_size,
+            max_files,
+            compression,
+        })
+    }
+}
+
+// same defaults as tracing-appender
+const DEFAULT_BUFFERED_LINES_LIMIT: usize = 128_000;
+const DEFAULT_SHUTDOWN_TIMEOUT: f64 = 1.0;
+const DEFAULT_THREAD_NAME: &str = "tracing-appender";
+
+#[pyclass(frozen, from_py_object)]
+#[derive(Clone)]
+pub struct NonBlocking {
+    pub lossy: bool,
+    pub buffered_lines_limit: usize,
+    pub shutdown_timeout: Duration,
+    pub thread_name: String,
+    // every FmtLayer gets its own, LOSSY and COMPLETE are shared objects
+    pub dropped_lines: Arc<AtomicU64>,
+}
+
+#[pymethods]
+impl NonBlocking {
+    #[new]
+    #[pyo3(signature = (
+        lossy = true,
+        *,
+        buffered_lines_limit = DEFAULT_BUFFERED_LINES_LIMIT,
+        shutdown_timeout = DEFAULT_SHUTDOWN_TIMEOUT,
+        thread_name = DEFAULT_THREAD_NAME.to_owned(),
+    ))]
+    fn new(
+        lossy: bool,
+        buffered_lines_limit: usize,
+        shutdown_timeout: f64,
+        thread_name: String,
+    ) -> PyResult<Self> {
+        if buffered_lines_limit == 0 {
+            return Err(PyValueError::new_err(
+                "buffered_lines_limit must be greater than 0",
+            ));
         }
+        let shutdown_timeout = Duration::try_from_secs_f64(shutdown_timeout)
+            .map_err(|e| PyValueError::new_err(format!("invalid shutdown_timeout: {e}")))?;
+        Ok(Self {
+            lossy,
+            buffered_lines_limit,
+            shutdown_timeout,
+            thread_name,
+            dropped_lines: Arc::default(),
+        })
+    }
+
+    #[classattr]
+    #[pyo3(name = "LOSSY")]
+    fn lossy_default() -> Self {
+        Self::with_defaults(true)
+    }
+
+    #[classattr]
+    #[pyo3(name = "COMPLETE")]
+    fn complete_default() -> Self {
+        Self::with_defaults(false)
+    }
+
+    #[getter]
+    fn lossy(&self) -> bool {
+        self.lossy
+    }
+
+    #[getter]
+    fn buffered_lines_limit(&self) -> usize {
+        self.buffered_lines_limit
+    }
+
+    #[getter]
+    fn shutdown_timeout(&self) -> f64 {
+        self.shutdown_timeout.as_secs_f64()
+    }
+
+    #[getter]
+    fn thread_name(&self) -> &str {
+        &self.thread_name
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "NonBlocking(lossy={}, buffered_lines_limit={}, shutdown_timeout={}, thread_name={:?})",
+            if self.lossy { "True" } else { "False" },
+            self.buffered_lines_limit,
+            self.shutdown_timeout.as_secs_f64(),
+            self.thread_name
+        )
     }
 }
 
-#[pyclass(rename_all = "UPPERCASE", from_py_object)]
-#[derive(Clone, Copy)]
-pub enum NonBlocking {
-    Lossy,
-    Complete,
+impl NonBlocking {
+    pub fn with_own_counter(self) -> Self {
+        Self {
+            dropped_lines: Arc::default(),
+            ..self
+        }
+    }
+
+    fn with_defaults(lossy: bool) -> Self {
+        Self {
+            lossy,
+            buffered_lines_limit: DEFAULT_BUFFERED_LINES_LIMIT,
+            shutdown_timeout: Duration::from_secs_f64(DEFAULT_SHUTDOWN_TIMEOUT),
+            thread_name: DEFAULT_THREAD_NAME.to_owned(),
+            dropped_lines: Arc::default(),
+        }
+    }
 }
@@ -0,0 +1,97 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::Value as Json;
+use tracing::Event;
+use tracing_subscriber::{
+    Registry,
+    fmt::{FmtContext, FormatFields, format::Writer},
+};
+
+use crate::layer::{
+    fmt::json::{flat_fields, json_line},
+    syslog::severity,
+};
+
+// gelf 1.1, the additional fields are prefixed with '_' and are either strings or numbers
+pub fn format_event<N>(
+    host: &str,
+    ctx: &FmtContext<'_, Registry, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+) -> fmt::Result
+where
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let meta = event.metadata();
+    let (message, fields) = flat_fields(ctx, event);
+
+    let message = match message {
+        Some(Json::String(message)) => message,
+        Some(message) => message.to_string(),
+        None => String::new(),
+    };
+    // short_message is required and can't be empty, the multi-line messages are also sent as full_message
+    let short_message = match message.lines().next() {
+        Some(line) if !line.is_empty() => line.to_owned(),
+        _ => meta.target().to_owned(),
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as f64
+        / 1000.0;
+
+    let mut object: Vec<(String, Json)> = vec![
+        ("version".to_owned(), "1.1".into()),
+        ("host".to_owned(), host.into()),
+        ("short_message".to_owned(), short_message.into()),
+    ];
+    if message.contains('\n') {
+        object.push(("full_message".to_owned(), message.into()));
+    }
+    object.push(("timestamp".to_owned(), timestamp.into()));
+    object.push(("level".to_owned(), severity(*meta.level()).into()));
+    object.push(("_logger".to_owned(), meta.target().into()));
+    if let Some(file) = meta.file() {
+        object.push(("_file".to_owned(), file.into()));
+    }
+    if let Some(line) = meta.line() {
+        object.push(("_line".to_owned(), line.into()));
+    }
+
+    for (name, value) in fields {
+        let name = additional_field(&name);
+        // the callsite fields take precedence
+        if object.iter().any(|(key, _)| *key == name) {
+            continue;
+        }
+        let value = match value {
+            Json::Number(_) | Json::String(_) => value,
+            other => other.to_string().into(),
+        };
+        object.push((name, value));
+    }
+    writeln!(writer, "{}", json_line(object))
+}
+
+// the names are ascii word characters, '.' and '-' (graylog's ^[\w\.\-]*$); `_id` is reserved
+fn additional_field(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name == "id" {
+        "__id".to_owned()
+    } else {
+        format!("_{name}")
+    }
+}
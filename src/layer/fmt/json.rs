@@ -0,0 +1,283 @@
+use std::{
+    fmt::{self, Debug},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde_json::{Map, Value as Json};
+use tracing::{
+    Event,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{
+    Registry,
+    field::RecordFields,
+    fmt::{FmtContext, FormatFields, FormattedFields, format::Writer, time::FormatTime},
+};
+
+use valuable::Value;
+use valuable_serde::Serializable;
+
+use crate::layer::fmt::event::EventFormat;
+
+#[pyclass(name = "Epoch", rename_all = "UPPERCASE", from_py_object)]
+#[derive(Clone, Copy)]
+pub enum PyEpoch {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+impl PyEpoch {
+    fn now(self) -> Json {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let value = match self {
+            Self::Seconds => elapsed.as_secs().into(),
+            Self::Milliseconds => elapsed.as_millis(),
+            Self::Microseconds => elapsed.as_micros(),
+            Self::Nanoseconds => elapsed.as_nanos(),
+        };
+        u64::try_from(value).map_or_else(|_| value.to_string().into(), Into::into)
+    }
+}
+
+// Format.JSON with options; the keys and the values are the same as in Format.JSON by default
+#[pyclass(name = "JsonFormat", frozen, from_py_object)]
+#[derive(Clone)]
+pub struct JsonFormat {
+    flatten_event: bool,
+    with_current_span: bool,
+    with_span_list: bool,
+    timestamp_key: String,
+    level_key: String,
+    message_key: String,
+    epoch: Option<PyEpoch>,
+}
+
+#[pymethods]
+impl JsonFormat {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        flatten_event = false,
+        with_current_span = true,
+        with_span_list = true,
+        timestamp_key = "timestamp".to_owned(),
+        level_key = "level".to_owned(),
+        message_key = "message".to_owned(),
+        epoch = None,
+    ))]
+    fn new(
+        flatten_event: bool,
+        with_current_span: bool,
+        with_span_list: bool,
+        timestamp_key: String,
+        level_key: String,
+        message_key: String,
+        epoch: Option<PyEpoch>,
+    ) -> PyResult<Self> {
+        if [&timestamp_key, &level_key, &message_key]
+            .iter()
+            .any(|key| key.is_empty())
+        {
+            return Err(PyValueError::new_err("the key names must not be empty"));
+        }
+        Ok(Self {
+            flatten_event,
+            with_current_span,
+            with_span_list,
+            timestamp_key,
+            level_key,
+            message_key,
+            epoch,
+        })
+    }
+}
+
+pub fn format_event<T, N>(
+    format: &EventFormat<T>,
+    options: &JsonFormat,
+    ctx: &FmtContext<'_, Registry, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+) -> fmt::Result
+where
+    T: FormatTime,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let meta = event.metadata();
+    // the keys are in the same order as in Format.JSON
+    let mut object: Vec<(String, Json)> = vec![];
+
+    // timer=None still disables the timestamp, the epoch replaces the formatted one, so the timer isn't called
+    let ts = match options.epoch {
+        Some(epoch) => format.has_timer.then(|| epoch.now()),
+        None => format.timestamp().map(Json::from),
+    };
+    if let Some(ts) = ts {
+        object.push((options.timestamp_key.clone(), ts));
+    }
+    if format.with_level {
+        object.push((options.level_key.clone(), meta.level().as_str().into()));
+    }
+
+    // the fields are recorded the same way as in Format.JSON
+    let mut fields = json_fields(event);
+    if let Some(message) = fields.remove("message") {
+        fields.insert(options.message_key.clone(), message);
+    }
+    if options.flatten_event {
+        object.extend(fields);
+    } else {
+        object.push(("fields".to_owned(), fields.into()));
+    }
+
+    if format.with_target {
+        object.push(("target".to_owned(), meta.target().into()));
+    }
+    if format.with_file
+        && let Some(file) = meta.file()
+    {
+        object.push(("filename".to_owned(), file.into()));
+    }
+    if format.with_line_number
+        && let Some(line) = meta.line()
+    {
+        object.push(("line_number".to_owned(), line.into()));
+    }
+
+    let spans = ctx
+        .event_scope()
+        .map(|scope| {
+            scope
+                .from_root()
+                .map(|span| {
+                    let mut fields = span
+                        .extensions()
+                        .get::<FormattedFields<N>>()
+                        .map(|fields| fields_of(|mut writer| writer.write_str(&fields.fields)))
+                        .unwrap_or_default();
+                    fields.insert("name".to_owned(), span.name().into());
+                    Json::Object(fields)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if options.with_current_span
+        && let Some(current) = spans.last()
+    {
+        object.push(("span".to_owned(), current.clone()));
+    }
+    if options.with_span_list && !spans.is_empty() {
+        object.push(("spans".to_owned(), spans.into()));
+    }
+
+    if format.with_thread_ids {
+        object.push((
+            "threadId".to_owned(),
+            format!("{:?}", thread::current().id()).into(),
+        ));
+    }
+
+    writeln!(writer, "{}", json_line(object))
+}
+
+// serde_json's maps are sorted, the keys are written in the given order here
+pub fn json_line<K: AsRef<str>>(object: Vec<(K, Json)>) -> String {
+    let object = object
+        .into_iter()
+        .map(|(key, value)| format!("{}:{value}", Json::from(key.as_ref())))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", object.join(","))
+}
+
+// the span fields are stored as a json object by JsonFields
+fn fields_of(format: impl FnOnce(Writer<'_>) -> fmt::Result) -> Map<String, Json> {
+    let mut json = String::new();
+    if format(Writer::new(&mut json)).is_err() {
+        return Map::new();
+    }
+    serde_json::from_str(&json).unwrap_or_default()
+}
+
+// the event fields as a json object, for the json-based formats and the layers that write json themselves;
+// like JsonFields, except that the integers that don't fit into 64 bits are strings instead of a panic
+pub fn json_fields(fields: impl RecordFields) -> Map<String, Json> {
+    let mut visitor = JsonVisitor::default();
+    fields.record(&mut visitor);
+    visitor.0
+}
+
+#[derive(Default)]
+struct JsonVisitor(Map<String, Json>);
+
+impl Visit for JsonVisitor {
+    fn record_value(&mut self, field: &Field, value: Value<'_>) {
+        let value = match value {
+            Value::I128(int) => {
+                i64::try_from(int).map_or_else(|_| int.to_string().into(), Into::into)
+            }
+            Value::U128(int) => {
+                u64::try_from(int).map_or_else(|_| int.to_string().into(), Into::into)
+            }
+            // lists and dicts are nested objects, like in Format.JSON
+            other => serde_json::to_value(Serializable::new(other))
+                .unwrap_or_else(|_| format!("{other:?}").into()),
+        };
+        self.0.insert(field.name().to_owned(), value);
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_owned(), format!("{value:?}").into());
+    }
+}
+
+// the fields of the spans, outermost first, and of the event, the inner ones replace the outer ones;
+// the event message is returned separately
+pub fn flat_fields<N>(
+    ctx: &FmtContext<'_, Registry, N>,
+    event: &Event<'_>,
+) -> (Option<Json>, Map<String, Json>)
+where
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let mut fields = Map::new();
+    if let Some(scope) = ctx.event_scope() {
+        for span in scope.from_root() {
+            if let Some(span_fields) = span.extensions().get::<FormattedFields<N>>() {
+                fields.extend(fields_of(|mut writer| {
+                    writer.write_str(&span_fields.fields)
+                }));
+            }
+        }
+    }
+    let mut event_fields = json_fields(event);
+    let message = event_fields.remove("message");
+    fields.extend(event_fields);
+    (message, fields)
+}
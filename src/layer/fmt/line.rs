@@ -0,0 +1,585 @@
+use std::{cell::OnceCell, fmt, path::Path, process};
+
+use pyo3::{exceptions::PyValueError, intern, prelude::*};
+use serde_json::Value as Json;
+use tracing::{Event, Level, Metadata};
+use tracing_subscriber::{
+    Registry,
+    fmt::{FmtContext, FormatFields, format::Writer, time::FormatTime},
+};
+
+use crate::{
+    imports::{get_threading_current_thread, get_threading_get_ident},
+    layer::fmt::{event::EventFormat, json::flat_fields},
+};
+
+// a logging.Formatter-like template, `%(levelname)-8s %(message)s` or `{levelname:<8} {message}`,
+// parsed once when FmtLayer is created
+#[derive(Clone)]
+pub struct LineFormat(Vec<Piece>);
+
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Literal(String),
+    Placeholder(Placeholder, Spec),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Placeholder {
+    Timestamp,
+    LevelName,
+    Level,
+    LevelNo,
+    Target,
+    Module,
+    FileName,
+    PathName,
+    File,
+    Line,
+    Function,
+    Thread,
+    ThreadName,
+    Process,
+    Message,
+    Spans,
+    Fields,
+    Field(String),
+}
+
+impl Placeholder {
+    fn new(name: &str) -> Self {
+        // the logging.LogRecord attribute names, and the tracing ones
+        match name {
+            "asctime" | "timestamp" => Self::Timestamp,
+            "levelname" => Self::LevelName,
+            "level" => Self::Level,
+            "levelno" => Self::LevelNo,
+            "name" | "target" => Self::Target,
+            "module" => Self::Module,
+            "filename" => Self::FileName,
+            "pathname" => Self::PathName,
+            "file" => Self::File,
+            "lineno" | "line" => Self::Line,
+            "funcName" => Self::Function,
+            "thread" => Self::Thread,
+            "threadName" => Self::ThreadName,
+            "process" => Self::Process,
+            "message" => Self::Message,
+            "spans" => Self::Spans,
+            "fields" => Self::Fields,
+            name => Self::Field(name.to_owned()),
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Self::LevelNo | Self::Line | Self::Thread | Self::Process
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Spec {
+    fill: char,
+    align: Align,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn apply(&self, value: &str) -> String {
+        let value = match self.precision {
+            Some(precision) => value.chars().take(precision).collect(),
+            None => value.to_owned(),
+        };
+        let padding = self.width.saturating_sub(value.chars().count());
+        let (left, right) = match self.align {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        let fill = |n| self.fill.to_string().repeat(n);
+        format!("{}{value}{}", fill(left), fill(right))
+    }
+}
+
+impl LineFormat {
+    // a complete `%(name)s` placeholder means the %-style, like logging.Formatter(style="%"), otherwise
+    // it's the {}-style, which may contain a literal `%(`
+    pub fn parse(template: &str) -> PyResult<Self> {
+        if has_percent_placeholder(template) {
+            Self::parse_percent(template)
+        } else {
+            Self::parse_braces(template)
+        }
+        .map_err(|err| PyValueError::new_err(format!("invalid line_format {template:?}: {err}")))
+    }
+
+    fn parse_percent(template: &str) -> Result<Self, String> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => {
+                    literal.push('%');
+                    continue;
+                }
+                Some('(') => {}
+                _ => return Err("expected %(name)s or %%".to_owned()),
+            }
+
+            let (name, spec) = percent_placeholder(&mut chars)?;
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+            }
+            pieces.push(Piece::Placeholder(Placeholder::new(&name), spec));
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Self(pieces))
+    }
+
+    fn parse_braces(template: &str) -> Result<Self, String> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => literal.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => literal.push('}'),
+                '}' => return Err("single '}'".to_owned()),
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => return Err(format!("unclosed {{{field}")),
+                        }
+                    }
+                    let (name, format_spec) = field.split_once(':').unwrap_or((&field, ""));
+                    // the conversion (`!r`, `!s`) doesn't matter, the values are already strings
+                    let name = name.split_once('!').map_or(name, |(name, _)| name);
+                    if name.is_empty() {
+                        return Err("the fields must be named".to_owned());
+                    }
+
+                    let placeholder = Placeholder::new(name);
+                    let spec = brace_spec(format_spec, placeholder.is_numeric())?;
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Placeholder(placeholder, spec));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Self(pieces))
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+// the rest of a placeholder after `%(`: the name, `)`, the %-flags, width, precision and the conversion,
+// e.g. `levelname)-8s` or `message).3s`
+fn percent_placeholder(chars: &mut Chars<'_>) -> Result<(String, Spec), String> {
+    let mut name = String::new();
+    loop {
+        match chars.next() {
+            Some(')') => break,
+            Some(c) => name.push(c),
+            None => return Err(format!("unclosed %({name}")),
+        }
+    }
+
+    let mut spec = Spec {
+        fill: ' ',
+        align: Align::Right,
+        width: 0,
+        precision: None,
+    };
+    while let Some(&flag) = chars.peek()
+        && matches!(flag, '-' | '0' | ' ' | '+' | '#')
+    {
+        match flag {
+            '-' => spec.align = Align::Left,
+            '0' => spec.fill = '0',
+            _ => {}
+        }
+        chars.next();
+    }
+    spec.width = number(chars).unwrap_or(0);
+    if chars.next_if_eq(&'.').is_some() {
+        spec.precision = Some(number(chars).unwrap_or(0));
+    }
+    match chars.next() {
+        Some(conversion) if "sdrifFeEgGxXoac".contains(conversion) => {}
+        _ => return Err(format!("missing the conversion after %({name})")),
+    }
+    // the left-aligned ones are padded with spaces, like in python
+    if matches!(spec.align, Align::Left) {
+        spec.fill = ' ';
+    }
+    Ok((name, spec))
+}
+
+fn has_percent_placeholder(template: &str) -> bool {
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '%'
+            && chars.next_if_eq(&'%').is_none()
+            && chars.next_if_eq(&'(').is_some()
+            && percent_placeholder(&mut chars.clone()).is_ok()
+        {
+            return true;
+        }
+    }
+    false
+}
+
+// [[fill]align][sign][#][0][width][,|_][.precision][type]
+fn brace_spec(format_spec: &str, numeric: bool) -> Result<Spec, String> {
+    let mut spec = Spec {
+        fill: ' ',
+        align: if numeric { Align::Right } else { Align::Left },
+        width: 0,
+        precision: None,
+    };
+    let align_of = |c| match c {
+        '<' => Some(Align::Left),
+        '>' => Some(Align::Right),
+        '^' => Some(Align::Center),
+        _ => None,
+    };
+
+    let mut chars = format_spec.chars().peekable();
+    let first = format_spec.chars().next();
+    let second = format_spec.chars().nth(1);
+    if let Some(align) = second.and_then(align_of) {
+        spec.fill = first.unwrap_or(' ');
+        spec.align = align;
+        chars.nth(1);
+    } else if let Some(align) = first.and_then(align_of) {
+        spec.align = align;
+        chars.next();
+    }
+    chars.next_if(|c| matches!(c, '+' | '-' | ' '));
+    chars.next_if_eq(&'#');
+    if chars.next_if_eq(&'0').is_some() {
+        spec.fill = '0';
+        spec.align = Align::Right;
+    }
+    spec.width = number(&mut chars).unwrap_or(0);
+    chars.next_if(|c| matches!(c, ',' | '_'));
+    if chars.next_if_eq(&'.').is_some() {
+        spec.precision = Some(number(&mut chars).ok_or("missing the precision")?);
+    }
+    chars.next_if(|c| "sdrifFeEgGxXobcn%".contains(*c));
+    if chars.next().is_some() {
+        return Err(format!("invalid format spec {format_spec:?}"));
+    }
+    Ok(spec)
+}
+
+fn number(chars: &mut Chars<'_>) -> Option<usize> {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+    digits.parse().ok()
+}
+
+// logging's level names and numbers, TRACE is below DEBUG
+fn stdlib_level(level: Level) -> (&'static str, u8) {
+    match level {
+        Level::TRACE => ("TRACE", 5),
+        Level::DEBUG => ("DEBUG", 10),
+        Level::INFO => ("INFO", 20),
+        Level::WARN => ("WARNING", 30),
+        Level::ERROR => ("ERROR", 40),
+    }
+}
+
+thread_local! {
+    // threading.get_ident() and threading.current_thread().name, read by the first event of the thread,
+    // so the gil isn't taken for each event
+    static PY_THREAD: OnceCell<Option<(u64, String)>> = const { OnceCell::new() };
+}
+
+fn py_thread<R>(f: impl FnOnce(&(u64, String)) -> R) -> Option<R> {
+    PY_THREAD.with(|thread| {
+        thread
+            .get_or_init(|| {
+                Python::try_attach(|py| {
+                    let ident = get_threading_get_ident(py).call0()?.extract()?;
+                    let current = get_threading_current_thread(py).call0()?;
+                    let name = current.getattr(intern!(py, "name"))?.extract()?;
+                    PyResult::Ok((ident, name))
+                })
+                .and_then(Result::ok)
+            })
+            .as_ref()
+            .map(f)
+    })
+}
+
+// the events of this crate are named `event {path}`, the path of the code object or the record's pathname,
+// the same path logging uses; the other ones, e.g. the FmtSpan events, only have the file
+fn pathname<'a>(meta: &Metadata<'a>) -> &'a str {
+    meta.name()
+        .strip_prefix("event ")
+        .or(meta.file())
+        .unwrap_or_default()
+}
+
+fn text(value: &Json) -> String {
+    match value {
+        Json::String(str) => str.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub fn format_event<T, N>(
+    format: &EventFormat<T>,
+    template: &LineFormat,
+    ctx: &FmtContext<'_, Registry, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+) -> fmt::Result
+where
+    T: FormatTime,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let meta = event.metadata();
+    let (message, fields) = flat_fields(ctx, event);
+
+    let mut line = String::new();
+    for piece in &template.0 {
+        let (placeholder, spec) = match piece {
+            Piece::Literal(literal) => {
+                line.push_str(literal);
+                continue;
+            }
+            Piece::Placeholder(placeholder, spec) => (placeholder, spec),
+        };
+        let value = match placeholder {
+            Placeholder::Timestamp => format.timestamp().unwrap_or_default(),
+            Placeholder::LevelName => stdlib_level(*meta.level()).0.to_owned(),
+            Placeholder::Level => meta.level().as_str().to_owned(),
+            Placeholder::LevelNo => stdlib_level(*meta.level()).1.to_string(),
+            Placeholder::Target => meta.target().to_owned(),
+            // like logging, the file name without the extension
+            Placeholder::Module => Path::new(pathname(meta))
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            Placeholder::FileName => Path::new(pathname(meta))
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            Placeholder::PathName => pathname(meta).to_owned(),
+            Placeholder::File => meta.file().unwrap_or_default().to_owned(),
+            Placeholder::Line => meta.line().map(|x| x.to_string()).unwrap_or_default(),
+            // the python targets are `module.qualname`, logging's funcName is the last part of the qualname;
+            // the stdlib records don't have it, their target is the logger name
+            Placeholder::Function => meta
+                .module_path()
+                .and_then(|module| meta.target().strip_prefix(module)?.strip_prefix('.'))
+                .and_then(|qualname| qualname.rsplit('.').next())
+                .unwrap_or_default()
+                .to_owned(),
+            Placeholder::Thread => py_thread(|(ident, _)| ident.to_string()).unwrap_or_default(),
+            Placeholder::ThreadName => py_thread(|(_, name)| name.clone()).unwrap_or_default(),
+            Placeholder::Process => process::id().to_string(),
+            Placeholder::Message => message.as_ref().map(text).unwrap_or_default(),
+            Placeholder::Spans => ctx
+                .event_scope()
+                .map(|scope| {
+                    scope
+                        .from_root()
+                        .map(|span| span.name())
+                        .collect::<Vec<_>>()
+                        .join(":")
+                })
+                .unwrap_or_default(),
+            Placeholder::Fields => fields
+                .iter()
+                .map(|(name, value)| format!("{name}={}", text(value)))
+                .collect::<Vec<_>>()
+                .join(" "),
+            // empty if the event and its spans don't have it
+            Placeholder::Field(name) => fields.get(name).map(text).unwrap_or_default(),
+        };
+        line.push_str(&spec.apply(&value));
+    }
+    writeln!(writer, "{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(template: &str) -> Vec<Piece> {
+        LineFormat::parse(template).unwrap().0
+    }
+
+    fn literal(text: &str) -> Piece {
+        Piece::Literal(text.to_owned())
+    }
+
+    fn placeholder(name: &str, align: Align, width: usize) -> Piece {
+        let spec = Spec {
+            fill: ' ',
+            align,
+            width,
+            precision: None,
+        };
+        Piece::Placeholder(Placeholder::new(name), spec)
+    }
+
+    // the template has a single placeholder, which is replaced by the value
+    fn render(template: &str, value: &str) -> String {
+        pieces(template)
+            .iter()
+            .map(|piece| match piece {
+                Piece::Literal(literal) => literal.clone(),
+                Piece::Placeholder(_, spec) => spec.apply(value),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn percent_style() {
+        assert_eq!(
+            pieces("%(asctime)s %(levelname)-8s %(name)s:%(lineno)d %(message)s"),
+            [
+                placeholder("asctime", Align::Right, 0),
+                literal(" "),
+                placeholder("levelname", Align::Left, 8),
+                literal(" "),
+                placeholder("name", Align::Right, 0),
+                literal(":"),
+                placeholder("lineno", Align::Right, 0),
+                literal(" "),
+                placeholder("message", Align::Right, 0),
+            ]
+        );
+        assert_eq!(
+            pieces("100%% %(message)s %%"),
+            [
+                literal("100% "),
+                placeholder("message", Align::Right, 0),
+                literal(" %"),
+            ]
+        );
+    }
+
+    #[test]
+    fn brace_style() {
+        assert_eq!(
+            pieces("{asctime} {levelname:<8} {{{message!r}}} {lineno}"),
+            [
+                placeholder("asctime", Align::Left, 0),
+                literal(" "),
+                placeholder("levelname", Align::Left, 8),
+                literal(" {"),
+                placeholder("message", Align::Left, 0),
+                literal("} "),
+                placeholder("lineno", Align::Right, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn literal_percent_paren_in_brace_style() {
+        assert_eq!(
+            pieces("{message} (%(not a placeholder"),
+            [
+                placeholder("message", Align::Left, 0),
+                literal(" (%(not a placeholder"),
+            ]
+        );
+        assert_eq!(pieces("50%(x) {name}")[0], literal("50%(x) "));
+        // an escaped %% isn't the start of a placeholder
+        assert_eq!(pieces("%%(name)s {name}")[0], literal("%%(name)s "));
+    }
+
+    #[test]
+    fn width_and_precision() {
+        assert_eq!(render("%(name)5s|", "ab"), "   ab|");
+        assert_eq!(render("%(name)-5s|", "ab"), "ab   |");
+        assert_eq!(render("%(name).3s|", "abcdef"), "abc|");
+        assert_eq!(render("%(name)6.2s|", "abcdef"), "    ab|");
+        assert_eq!(render("%(name).0s|", "abcdef"), "|");
+        assert_eq!(render("%(name)2s|", "abcdef"), "abcdef|");
+        assert_eq!(render("%(lineno)05d|", "42"), "00042|");
+        // like python, `-` wins over `0`
+        assert_eq!(render("%(lineno)-05d|", "42"), "42   |");
+
+        assert_eq!(render("{name:5}|", "ab"), "ab   |");
+        assert_eq!(render("{lineno:5}|", "42"), "   42|");
+        assert_eq!(render("{lineno:05}|", "42"), "00042|");
+        assert_eq!(render("{name:>6.2}|", "abcdef"), "    ab|");
+        assert_eq!(render("{name:^6}|", "ab"), "  ab  |");
+        assert_eq!(render("{name:^7}|", "ab"), "  ab   |");
+    }
+
+    #[test]
+    fn fill() {
+        assert_eq!(render("{name:*^7}", "ab"), "**ab***");
+        assert_eq!(render("{name:-<5}", "ab"), "ab---");
+        assert_eq!(render("{name:<<5}", "ab"), "ab<<<");
+        assert_eq!(render("{name:0>5}", "ab"), "000ab");
+        assert_eq!(render("{name:é>4}", "ab"), "ééab");
+        // the width is in characters, not bytes
+        assert_eq!(render("{name:4}|", "éé"), "éé  |");
+    }
+
+    #[test]
+    fn percent_errors() {
+        let error = |template| LineFormat::parse_percent(template).err().unwrap();
+        assert_eq!(error("%(asctime)s %(name"), "unclosed %(name");
+        assert_eq!(
+            error("%(asctime)s %(name)"),
+            "missing the conversion after %(name)"
+        );
+        assert_eq!(
+            error("%(asctime)s %(name)-8"),
+            "missing the conversion after %(name)"
+        );
+        assert_eq!(error("%(asctime)s %d"), "expected %(name)s or %%");
+        assert_eq!(error("%(asctime)s %"), "expected %(name)s or %%");
+        assert!(LineFormat::parse("%(asctime)s %(name)").is_err());
+    }
+
+    #[test]
+    fn brace_errors() {
+        let error = |template| LineFormat::parse_braces(template).err().unwrap();
+        assert_eq!(error("{message"), "unclosed {message");
+        assert_eq!(error("message}"), "single '}'");
+        assert_eq!(error("{}"), "the fields must be named");
+        assert_eq!(error("{!r}"), "the fields must be named");
+        assert_eq!(error("{name:.}"), "missing the precision");
+        assert_eq!(error("{name:8q}"), "invalid format spec \"8q\"");
+        assert!(LineFormat::parse("{message").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::{fmt, thread};
+
+use tracing::{Event, span};
+use tracing_subscriber::{
+    Registry,
+    field::RecordFields,
+    fmt::{FmtContext, FormatFields, FormattedFields, format::Writer, time::FormatTime},
+};
+
+use crate::layer::{fields::TextVisitor, fmt::event::EventFormat};
+
+// `ts=... level=info target=... msg="..." span.key=value key=value`
+pub fn format_event<T, N>(
+    format: &EventFormat<T>,
+    ctx: &FmtContext<'_, Registry, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+) -> fmt::Result
+where
+    T: FormatTime,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let meta = event.metadata();
+    let mut pairs = LogfmtPairs::default();
+
+    if let Some(ts) = format.timestamp() {
+        pairs.push("ts", &ts);
+    }
+    if format.with_level {
+        pairs.push("level", &meta.level().as_str().to_lowercase());
+    }
+    if format.with_target {
+        pairs.push("target", meta.target());
+    }
+    if format.with_file
+        && let Some(file) = meta.file()
+    {
+        pairs.push("file", file);
+    }
+    if format.with_line_number
+        && let Some(line) = meta.line()
+    {
+        pairs.push("line", &line.to_string());
+    }
+    if format.with_thread_ids {
+        pairs.push("thread_id", &format!("{:?}", thread::current().id()));
+    }
+
+    let mut visitor = TextVisitor::default();
+    event.record(&mut visitor);
+    if let Some(message) = &visitor.message {
+        pairs.push("msg", message);
+    }
+
+    // the span fields are already formatted by LogfmtFields, they're prefixed with the span name
+    if let Some(scope) = ctx.event_scope() {
+        for span in scope.from_root() {
+            if let Some(fields) = span.extensions().get::<FormattedFields<N>>() {
+                for pair in fields.fields.lines() {
+                    pairs.push_raw(&format!("{}.{pair}", key(span.name())));
+                }
+            }
+        }
+    }
+
+    for (name, value) in &visitor.fields {
+        pairs.push(name, value);
+    }
+    writeln!(writer, "{}", pairs.0)
+}
+
+// span fields, one `key=value` pair per line, so they can be prefixed when the event is formatted
+pub struct LogfmtFields;
+
+impl<'writer> FormatFields<'writer> for LogfmtFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = TextVisitor::default();
+        fields.record(&mut visitor);
+
+        let message = visitor.message.map(|message| ("message", message));
+        let pairs = message
+            .into_iter()
+            .chain(visitor.fields)
+            .map(|(name, value)| format!("{}={}", key(name), value_of(&value)))
+            .collect::<Vec<_>>();
+        write!(writer, "{}", pairs.join("\n"))
+    }
+
+    fn add_fields(
+        &self,
+        current: &'writer mut FormattedFields<Self>,
+        fields: &span::Record<'_>,
+    ) -> fmt::Result {
+        if !current.fields.is_empty() {
+            current.fields.push('\n');
+        }
+        self.format_fields(current.as_writer(), fields)
+    }
+}
+
+#[derive(Default)]
+struct LogfmtPairs(String);
+
+impl LogfmtPairs {
+    fn push(&mut self, name: &str, value: &str) {
+        self.push_raw(&format!("{}={}", key(name), value_of(value)));
+    }
+
+    fn push_raw(&mut self, pair: &str) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        self.0.push_str(pair);
+    }
+}
+
+// keys can't contain spaces, '=' and '"'
+fn key(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_whitespace() || c.is_control() || matches!(c, '=' | '"') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+// quoted if it's empty or has spaces, '=' or '"', the control characters are escaped
+fn value_of(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.contains(|c: char| c.is_whitespace() || c.is_control() || matches!(c, '=' | '"'));
+    if !needs_quotes {
+        return value.to_owned();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
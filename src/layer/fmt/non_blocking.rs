@@ -0,0 +1,255 @@
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use pyo3::{ffi, prelude::*};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::layer::{Flush, fmt::file::NonBlocking};
+
+thread_local! {
+    static IN_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+enum Msg {
+    Line(Vec<u8>),
+    Flush(SyncSender<io::Result<()>>),
+    // stops the worker and hands its state back, so it can be restarted after a fork
+    Park(SyncSender<Parked>),
+    Shutdown,
+}
+
+type Parked = (Box<dyn Write + Send>, Receiver<Msg>);
+
+// tracing-appender's worker can't be flushed without stopping it, so it's replaced with this one
+#[derive(Clone)]
+pub struct NonBlockingWriter {
+    sender: SyncSender<Msg>,
+    lossy: bool,
+    dropped_lines: Arc<AtomicU64>,
+}
+
+pub fn non_blocking<W: Write + Send + 'static>(
+    writer: W,
+    config: &NonBlocking,
+    log_internal_errors: bool,
+) -> io::Result<(NonBlockingWriter, WorkerGuard)> {
+    let (sender, receiver) = mpsc::sync_channel(config.buffered_lines_limit);
+    let done = spawn(Box::new(writer), receiver, &config.thread_name)?;
+
+    Ok((
+        NonBlockingWriter {
+            sender: sender.clone(),
+            lossy: config.lossy,
+            dropped_lines: config.dropped_lines.clone(),
+        },
+        WorkerGuard {
+            sender,
+            shutdown_timeout: config.shutdown_timeout,
+            thread_name: config.thread_name.clone(),
+            done: Mutex::new(done),
+            parked: Mutex::new(None),
+            log_internal_errors,
+        },
+    ))
+}
+
+// the returned receiver gets a message when the worker stops
+fn spawn(
+    writer: Box<dyn Write + Send>,
+    receiver: Receiver<Msg>,
+    thread_name: &str,
+) -> io::Result<Receiver<()>> {
+    let (done_sender, done) = mpsc::sync_channel(1);
+    thread::Builder::new()
+        .name(thread_name.to_owned())
+        .spawn(move || {
+            IN_WORKER.set(true);
+            work(writer, receiver);
+            let _ = done_sender.send(());
+        })?;
+    Ok(done)
+}
+
+// io errors are ignored, like in tracing-appender, there's nowhere to report them
+fn work(mut writer: Box<dyn Write + Send>, receiver: Receiver<Msg>) {
+    while let Ok(mut msg) = receiver.recv() {
+        // everything that's queued is written before flushing the writer,
+        // flushing after each line would defeat the purpose of buffering in the writer
+        loop {
+            match msg {
+                Msg::Line(line) => {
+                    let _ = writer.write_all(&line);
+                }
+                Msg::Flush(ack) => {
+                    let _ = ack.send(writer.flush());
+                }
+                Msg::Park(ack) => {
+                    let _ = writer.flush();
+                    let _ = ack.send((writer, receiver));
+                    return;
+                }
+                Msg::Shutdown => {
+                    let _ = writer.flush();
+                    return;
+                }
+            }
+            match receiver.try_recv() {
+                Ok(next) => msg = next,
+                Err(_) => break,
+            }
+        }
+        let _ = writer.flush();
+    }
+}
+
+impl Write for NonBlockingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // the worker would wait for itself, e.g. if a python file's write() logs something
+        if IN_WORKER.get() {
+            self.dropped_lines.fetch_add(1, Ordering::Relaxed);
+            return Ok(buf.len());
+        }
+        let msg = Msg::Line(buf.to_vec());
+        if self.lossy {
+            match self.sender.try_send(msg) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    self.dropped_lines.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(TrySendError::Disconnected(_)) => return Err(io::ErrorKind::BrokenPipe.into()),
+            }
+        } else {
+            match self.sender.try_send(msg) {
+                Ok(()) => {}
+                Err(TrySendError::Full(msg)) => send_detached(&self.sender, msg)?,
+                Err(TrySendError::Disconnected(_)) => return Err(io::ErrorKind::BrokenPipe.into()),
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// the events are usually emitted with the gil held, and the worker may need it to write the queued lines
+// (e.g. to a python file object), so the gil is released while waiting for a free slot
+fn send_detached(sender: &SyncSender<Msg>, msg: Msg) -> io::Result<()> {
+    let send = || {
+        sender
+            .send(msg)
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    };
+    // SAFETY: the thread state is only set while the thread is attached
+    if unsafe { ffi::compat::PyThreadState_GetUnchecked() }.is_null() {
+        send()
+    } else {
+        // SAFETY: the thread is attached, see above
+        unsafe { Python::assume_attached() }.detach(send)
+    }
+}
+
+impl<'a> MakeWriter<'a> for NonBlockingWriter {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+pub struct WorkerGuard {
+    sender: SyncSender<Msg>,
+    shutdown_timeout: Duration,
+    thread_name: String,
+    // receivers are !Sync, and the guards are shared
+    done: Mutex<Receiver<()>>,
+    // the worker's state between before_fork and after_fork
+    parked: Mutex<Option<Parked>>,
+    // same as the layer's option, the errors are printed to stderr
+    log_internal_errors: bool,
+}
+
+impl Flush for WorkerGuard {
+    // waits until everything that was written before the call is written to the underlying writer
+    fn flush(&self) -> Result<(), String> {
+        let (ack, flushed) = mpsc::sync_channel(1);
+        self.sender
+            .send(Msg::Flush(ack))
+            .map_err(|_| "the non-blocking worker has stopped".to_owned())?;
+        match flushed.recv() {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err("the non-blocking worker has stopped".to_owned()),
+        }
+    }
+
+    // the child process only has the thread that forked, so the worker is stopped before the fork
+    // and started again in both processes; the lines queued in between are written by both of them
+    fn before_fork(&self) {
+        let (ack, parked) = mpsc::sync_channel(1);
+        if self.sender.send(Msg::Park(ack)).is_ok()
+            && let Ok(parked) = parked.recv()
+        {
+            *self.parked.lock().unwrap() = Some(parked);
+        }
+    }
+
+    fn after_fork(&self) {
+        let Some((writer, receiver)) = self.parked.lock().unwrap().take() else {
+            return;
+        };
+        match spawn(writer, receiver, &self.thread_name) {
+            Ok(done) => *self.done.lock().unwrap() = done,
+            Err(err) => {
+                if self.log_internal_errors {
+                    eprintln!("tracing: failed to restart the non-blocking writer: {err}");
+                }
+            }
+        }
+    }
+}
+
+impl Drop for WorkerGuard {
+    // the queued lines are written before the shutdown message, unless it takes too long
+    fn drop(&mut self) {
+        let deadline = Instant::now() + self.shutdown_timeout;
+        let mut msg = Msg::Shutdown;
+        loop {
+            match self.sender.try_send(msg) {
+                Ok(()) => break,
+                Err(TrySendError::Disconnected(_)) => return,
+                Err(TrySendError::Full(returned)) => {
+                    if Instant::now() >= deadline {
+                        return;
+                    }
+                    msg = returned;
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+        }
+        match self
+            .done
+            .get_mut()
+            .unwrap()
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => {}
+            Err(RecvTimeoutError::Timeout) => {
+                if self.log_internal_errors {
+                    eprintln!(
+                        "tracing: timed out while waiting for the non-blocking writer to finish"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    sync::Arc,
+};
+
+use pyo3::{intern, prelude::*};
+use tracing_subscriber::fmt::MakeWriter;
+
+// any python object with write(), like io.StringIO or the sys.stderr replaced by pytest or jupyter;
+// the lines are decoded as utf-8 and written as strings, flush() is called after each write if it exists
+pub struct PyFile {
+    object: Arc<Py<PyAny>>,
+    buffer: Vec<u8>,
+    // the non-blocking worker writes everything that's queued before flushing,
+    // so the whole batch is a single write() call and the gil is acquired once
+    buffered: bool,
+}
+
+impl PyFile {
+    pub fn new(object: Arc<Py<PyAny>>, buffered: bool) -> Self {
+        Self {
+            object,
+            buffer: vec![],
+            buffered,
+        }
+    }
+
+    fn write_to_python(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.buffer).into_owned();
+        self.buffer.clear();
+        // write() may log something itself, directly or through LoggingHandler;
+        // that line is dropped instead of calling write() again
+        if WRITING.replace(true) {
+            return Ok(());
+        }
+        let _writing = Writing;
+
+        // the lines written while the interpreter is shutting down are lost
+        Python::try_attach(|py| {
+            let object = self.object.bind(py);
+            object.call_method1(intern!(py, "write"), (text,))?;
+            if object.hasattr(intern!(py, "flush"))? {
+                object.call_method0(intern!(py, "flush"))?;
+            }
+            Ok::<_, PyErr>(())
+        })
+        .ok_or_else(|| io::Error::other("the python interpreter is not running"))?
+        .map_err(io::Error::other)
+    }
+}
+
+thread_local! {
+    static WRITING: Cell<bool> = const { Cell::new(false) };
+}
+
+// resets the flag even if write() panics
+struct Writing;
+
+impl Drop for Writing {
+    fn drop(&mut self) {
+        WRITING.set(false);
+    }
+}
+
+impl Write for PyFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if !self.buffered {
+            self.write_to_python()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_to_python()
+    }
+}
+
+impl<'a> MakeWriter<'a> for PyFile {
+    type Writer = PyFile;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Self::new(self.object.clone(), self.buffered)
+    }
+}
@@ -0,0 +1,174 @@
+#[cfg(unix)]
+use std::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    sync::{Arc, Mutex, RwLock, Weak},
+};
+
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyCFunction};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::layer::fork::lock_layers;
+
+// every file opened from a path, so they can be reopened after logrotate moves them
+static FILES: Mutex<Vec<Weak<Inner>>> = Mutex::new(vec![]);
+
+// taken by the thread that forks, reopen_logs() holds it without the gil
+#[cfg(unix)]
+pub fn try_lock_for_fork() -> Option<impl Any> {
+    FILES.try_lock().ok()
+}
+
+struct Inner {
+    path: String,
+    // the lines are written under the read lock, so the file is never swapped in the middle of a line;
+    // both are taken inside of fork::lock_layers(), so the lock isn't held while the process forks
+    file: RwLock<File>,
+}
+
+#[derive(Clone)]
+pub struct ReopenableFile(Arc<Inner>);
+
+fn open(path: &str) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl ReopenableFile {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let inner = Arc::new(Inner {
+            path: path.to_owned(),
+            file: RwLock::new(open(path)?),
+        });
+        let mut files = FILES.lock().unwrap();
+        files.retain(|file| file.strong_count() > 0);
+        files.push(Arc::downgrade(&inner));
+        Ok(Self(inner))
+    }
+
+    // the old file is kept if the new one can't be opened
+    fn reopen(&self) -> io::Result<()> {
+        let file = open(&self.0.path)?;
+        let _fork = lock_layers();
+        *self.0.file.write().unwrap() = file;
+        Ok(())
+    }
+}
+
+impl Write for &ReopenableFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _fork = lock_layers();
+        (&*self.0.file.read().unwrap()).write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let _fork = lock_layers();
+        (&*self.0.file.read().unwrap()).write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _fork = lock_layers();
+        (&*self.0.file.read().unwrap()).flush()
+    }
+}
+
+// the non-blocking worker owns its writer
+impl Write for ReopenableFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self).write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        (&*self).write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self).flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for ReopenableFile {
+    type Writer = &'a ReopenableFile;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self
+    }
+}
+
+fn reopen_logs() -> Result<(), String> {
+    let files = FILES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(Weak::upgrade)
+        .collect::<Vec<_>>();
+    let errors = files
+        .into_iter()
+        .filter_map(|inner| {
+            let path = inner.path.clone();
+            ReopenableFile(inner)
+                .reopen()
+                .err()
+                .map(|err| format!("{path}: {err}"))
+        })
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("failed to reopen {}", errors.join(", ")))
+    }
+}
+
+// reopens the files the layers write to by path, for logrotate's `create` mode
+#[pyfunction(name = "reopen_logs")]
+pub fn py_reopen_logs(py: Python<'_>) -> PyResult<()> {
+    py.detach(reopen_logs).map_err(PyRuntimeError::new_err)
+}
+
+// the installed handler, so installing it again doesn't chain it to itself
+#[cfg(unix)]
+static SIGHUP_HANDLER: Mutex<Option<Py<PyCFunction>>> = Mutex::new(None);
+#[cfg(unix)]
+static SIGHUP_LOG_ERRORS: AtomicBool = AtomicBool::new(true);
+
+// python runs signal handlers in the main thread between the bytecodes, so nothing special is needed;
+// the previous handler is called afterwards, if there was one
+#[cfg(unix)]
+#[pyfunction(name = "reopen_logs_on_sighup")]
+#[pyo3(signature = (*, log_internal_errors = true))]
+pub fn py_reopen_logs_on_sighup(py: Python<'_>, log_internal_errors: bool) -> PyResult<()> {
+    SIGHUP_LOG_ERRORS.store(log_internal_errors, Ordering::Relaxed);
+
+    let signal = py.import("signal")?;
+    let sighup = signal.getattr("SIGHUP")?;
+    let previous = signal.call_method1("getsignal", (&sighup,))?;
+    let mut installed = SIGHUP_HANDLER.lock().unwrap();
+    if installed
+        .as_ref()
+        .is_some_and(|handler| handler.bind(py).is(&previous))
+    {
+        return Ok(());
+    }
+
+    let previous = previous.unbind();
+    let handler = PyCFunction::new_closure(py, None, None, move |args, kwargs| {
+        let py = args.py();
+        // raising here would interrupt whatever the main thread is doing
+        if let Err(err) = py.detach(reopen_logs)
+            && SIGHUP_LOG_ERRORS.load(Ordering::Relaxed)
+        {
+            eprintln!("tracing: {err}");
+        }
+        let previous = previous.bind(py);
+        if previous.is_callable() {
+            previous.call(args, kwargs)?;
+        }
+        Ok::<_, PyErr>(())
+    })?;
+    signal.call_method1("signal", (sighup, &handler))?;
+    *installed = Some(handler.unbind());
+    Ok(())
+}
@@ -0,0 +1,510 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::SystemTime,
+};
+
+use time::{
+    Date, Duration, OffsetDateTime, Time, format_description::BorrowedFormatItem,
+    macros::format_description, parsing::Parsed,
+};
+
+use crate::layer::{
+    Flush,
+    fmt::{
+        file::PyRollingLog,
+        rotation::{PyCompression, PyRotation},
+    },
+    fork::ForkSafeMutex,
+};
+
+const MINUTELY: &[BorrowedFormatItem<'_>] =
+    format_description!("[year]-[month]-[day]-[hour]-[minute]");
+const HOURLY: &[BorrowedFormatItem<'_>] = format_description!("[year]-[month]-[day]-[hour]");
+const DAILY: &[BorrowedFormatItem<'_>] = format_description!("[year]-[month]-[day]");
+
+// replaces tracing-appender's RollingFileAppender, which can't rotate by size;
+// the file names are the same as tracing-appender ones if size-based rotation is not used
+pub struct RollingWriter {
+    config: Arc<PyRollingLog>,
+    file: Option<File>,
+    // shared with the compression thread, so it doesn't delete the current file
+    current: Arc<ForkSafeMutex<String>>,
+    compressor: Option<Arc<Compressor>>,
+    size: u64,
+    // start of the current period, None for Rotation.NEVER
+    period: Option<OffsetDateTime>,
+    // number of the file within the current period, incremented when the file reaches max_size
+    index: u32,
+}
+
+impl RollingWriter {
+    pub fn new(config: PyRollingLog, log_internal_errors: bool) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let config = Arc::new(config);
+        let current = Arc::<ForkSafeMutex<String>>::default();
+        let compressor = config.compression.map(|compression| {
+            Arc::new(Compressor {
+                config: config.clone(),
+                compression,
+                current: current.clone(),
+                queue: ForkSafeMutex::default(),
+                thread: Mutex::default(),
+                log_internal_errors,
+            })
+        });
+        let mut writer = Self {
+            config,
+            file: None,
+            current,
+            compressor,
+            size: 0,
+            period: None,
+            index: 0,
+        };
+        writer.open(OffsetDateTime::now_utc())?;
+
+        // the files are pruned after the compression, otherwise the file that's being compressed is counted twice
+        match &writer.compressor {
+            Some(compressor) => {
+                let date = writer.date();
+                compressor.push(compressor.leftovers(date.as_deref()));
+            }
+            None => {
+                let current = writer.file_name();
+                prune(&writer.config, |name| name == current);
+            }
+        }
+        Ok(writer)
+    }
+
+    // stops the compression thread when it's dropped, None if the files are not compressed
+    pub fn compression_guard(&self) -> Option<CompressionGuard> {
+        self.compressor.clone().map(CompressionGuard)
+    }
+
+    fn open(&mut self, now: OffsetDateTime) -> io::Result<()> {
+        let period = period_start(self.config.rotation, now);
+        if period != self.period {
+            self.period = period;
+            self.index = 0;
+        }
+
+        // the files that are already full are skipped, e.g. after a restart, and compressed if they weren't;
+        // so are the compressed ones, their archive would be replaced otherwise
+        loop {
+            let path = self.path();
+            let metadata = fs::metadata(&path);
+            let compressed = self.config.compression.is_some_and(|compression| {
+                let mut archive = path.clone().into_os_string();
+                archive.push(".");
+                archive.push(compression.extension());
+                Path::new(&archive).exists()
+            });
+            let size = metadata.as_ref().map_or(0, |meta| meta.len());
+            if !compressed && self.config.max_size.is_none_or(|max_size| size < max_size) {
+                // before the file is created, so the compression thread never sees it as a rotated one
+                *self.current.lock() = self.file_name();
+                self.file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+                self.size = size;
+                break;
+            }
+            if let Some(compressor) = &self.compressor
+                && metadata.is_ok()
+            {
+                compressor.push(Some(path));
+            }
+            self.index += 1;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self, now: OffsetDateTime) -> io::Result<()> {
+        let closed = self.file.take().map(|mut file| {
+            let _ = file.flush();
+            self.path()
+        });
+        if period_start(self.config.rotation, now) == self.period {
+            self.index += 1;
+        }
+        self.open(now)?;
+
+        match &self.compressor {
+            Some(compressor) => compressor.push(closed),
+            None => {
+                let current = self.file_name();
+                prune(&self.config, |name| name == current);
+            }
+        }
+        Ok(())
+    }
+
+    fn date(&self) -> Option<String> {
+        self.period
+            .and_then(|period| format_period(self.config.rotation, period))
+    }
+
+    fn file_name(&self) -> String {
+        let date = self.date();
+        let index = (self.index > 0).then(|| self.index.to_string());
+
+        [
+            Some(self.config.prefix.clone()),
+            date,
+            index,
+            self.config.suffix.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(".")
+    }
+
+    fn path(&self) -> PathBuf {
+        Path::new(&self.config.dir).join(self.file_name())
+    }
+}
+
+// only the names built by file_name() are matched, `prefix.<date>[.<index>][.suffix]`, so the other files
+// in the same directory are never deleted; the date must have the rotation's format, like in tracing-appender.
+// the compressed files are matched too, the ones that are being compressed are not
+fn is_log_file(config: &PyRollingLog, name: &str) -> bool {
+    LogFileName::parse(config, name).is_some()
+}
+
+// the parts of a name built by file_name(), the index is only validated
+struct LogFileName<'a> {
+    date: Option<&'a str>,
+    compressed: bool,
+}
+
+impl<'a> LogFileName<'a> {
+    fn parse(config: &PyRollingLog, name: &'a str) -> Option<Self> {
+        if name.ends_with(TMP_EXTENSION) {
+            return None;
+        }
+        let stripped = [PyCompression::Gzip, PyCompression::Zstd]
+            .into_iter()
+            .find_map(|compression| {
+                name.strip_suffix(compression.extension())?
+                    .strip_suffix('.')
+            });
+        let compressed = stripped.is_some();
+
+        // the empty parts are skipped by file_name(), the other ones are separated by dots
+        let mut rest = stripped.unwrap_or(name);
+        if !config.prefix.is_empty() {
+            rest = rest.strip_prefix(config.prefix.as_str())?;
+        }
+        if let Some(suffix) = config.suffix.as_deref().filter(|x| !x.is_empty()) {
+            rest = rest.strip_suffix(suffix)?;
+            if !rest.is_empty() {
+                rest = rest.strip_suffix('.')?;
+            }
+        }
+        let parts = if rest.is_empty() {
+            vec![]
+        } else if config.prefix.is_empty() {
+            rest.split('.').collect()
+        } else {
+            rest.strip_prefix('.')?.split('.').collect()
+        };
+
+        let mut parts = parts.into_iter();
+        let format = match config.rotation {
+            PyRotation::Minutely => Some(MINUTELY),
+            PyRotation::Hourly => Some(HOURLY),
+            PyRotation::Daily | PyRotation::Weekly => Some(DAILY),
+            PyRotation::Never => None,
+        };
+        let date = match format {
+            Some(format) => Some(parts.next().filter(|date| is_date(date, format))?),
+            None => None,
+        };
+        if let Some(index) = parts.next()
+            && (index.starts_with('0')
+                || !index.bytes().all(|x| x.is_ascii_digit())
+                || index.parse::<u32>().is_err())
+        {
+            return None;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self { date, compressed })
+    }
+}
+
+fn is_date(date: &str, format: &[BorrowedFormatItem<'_>]) -> bool {
+    let mut parsed = Parsed::new();
+    parsed
+        .parse_items(date.as_bytes(), format)
+        .is_ok_and(<[u8]>::is_empty)
+        && Date::try_from(parsed).is_ok()
+}
+
+// deletes the oldest files, so there are at most max_files of them, including the current one;
+// errors are ignored, the files may be deleted by someone else.
+// is_current is checked again before each deletion, as the writer may rotate while the directory is scanned
+fn prune(config: &PyRollingLog, is_current: impl Fn(&str) -> bool) {
+    let Some(max_files) = config.max_files else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&config.dir) else {
+        return;
+    };
+
+    let mut files = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|x| x.is_file()))
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if is_current(&name) || !is_log_file(config, &name) {
+                return None;
+            }
+            let modified = entry.metadata().and_then(|x| x.modified()).ok()?;
+            Some((modified, name, entry.path()))
+        })
+        .collect::<Vec<(SystemTime, String, PathBuf)>>();
+
+    let excess = (files.len() + 1).saturating_sub(max_files);
+    files.sort();
+    for (_, name, path) in files.into_iter().take(excess) {
+        if !is_current(&name) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+const TMP_EXTENSION: &str = ".tmp";
+
+// compresses the rotated files one at a time, not blocking the writer, which may be called with the gil held;
+// the thread is started when there's something to compress and stops when the queue is empty
+struct Compressor {
+    config: Arc<PyRollingLog>,
+    compression: PyCompression,
+    current: Arc<ForkSafeMutex<String>>,
+    queue: ForkSafeMutex<Queue>,
+    // the last started thread, it's joined by the guard
+    thread: Mutex<Option<JoinHandle<()>>>,
+    log_internal_errors: bool,
+}
+
+#[derive(Default)]
+struct Queue {
+    paths: VecDeque<PathBuf>,
+    running: bool,
+}
+
+impl Compressor {
+    fn push(self: &Arc<Self>, paths: impl IntoIterator<Item = PathBuf>) {
+        // the queue is released before joining, it's locked by the writer on every rotation
+        {
+            let mut queue = self.queue.lock();
+            queue.paths.extend(paths);
+            if queue.running || queue.paths.is_empty() {
+                return;
+            }
+            queue.running = true;
+        }
+
+        let mut thread = self.thread.lock().unwrap();
+        // it has emptied the queue, so it's about to return
+        if let Some(previous) = thread.take() {
+            let _ = previous.join();
+        }
+        let this = self.clone();
+        match thread::Builder::new()
+            .name("tracing-compress".to_owned())
+            .spawn(move || this.run())
+        {
+            Ok(handle) => *thread = Some(handle),
+            // the files stay in the queue until the next rotation
+            Err(err) => {
+                self.queue.lock().running = false;
+                self.report(&format!("failed to start the compression thread: {err}"));
+            }
+        }
+    }
+
+    fn run(&self) {
+        loop {
+            let path = {
+                let mut queue = self.queue.lock();
+                let Some(path) = queue.paths.pop_front() else {
+                    queue.running = false;
+                    return;
+                };
+                path
+            };
+            // the file may have been pruned while it was waiting
+            if let Err(err) = compress(&path, self.compression)
+                && err.kind() != io::ErrorKind::NotFound
+            {
+                self.report(&format!("failed to compress {}: {err}", path.display()));
+            }
+            // the writer is blocked only while the names are compared, not during the whole scan
+            prune(&self.config, |name| *self.current.lock() == name);
+        }
+    }
+
+    // the leftovers of a process that exited before compressing all of its files: the archives that weren't
+    // completed are deleted, and the files that weren't compressed are returned, the oldest ones first;
+    // the files of the current period are left to the writer, it compresses the full ones when it skips them
+    fn leftovers(&self, date: Option<&str>) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.config.dir) else {
+            return vec![];
+        };
+
+        let mut files = vec![];
+        for entry in entries.filter_map(Result::ok) {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if let Some(archive) = name.strip_suffix(TMP_EXTENSION) {
+                if LogFileName::parse(&self.config, archive).is_some_and(|x| x.compressed) {
+                    let _ = fs::remove_file(entry.path());
+                }
+            } else if let Some(file) = LogFileName::parse(&self.config, &name)
+                && !file.compressed
+                && file.date != date
+            {
+                let modified = entry.metadata().and_then(|x| x.modified()).ok();
+                files.push((modified, entry.path()));
+            }
+        }
+        files.sort();
+        files.into_iter().map(|(_, path)| path).collect()
+    }
+
+    fn report(&self, message: &str) {
+        if self.log_internal_errors {
+            eprintln!("tracing: {message}");
+        }
+    }
+}
+
+pub struct CompressionGuard(Arc<Compressor>);
+
+impl Flush for CompressionGuard {
+    // waits until the files rotated before the call are compressed
+    fn flush(&self) -> Result<(), String> {
+        let thread = self.0.thread.lock().unwrap().take();
+        match thread {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| "the compression thread has panicked".to_owned()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CompressionGuard {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+// `name.log` is compressed to `name.log.gz`, the original file is deleted afterwards;
+// the compressed file is renamed only when it's complete, so a crash doesn't leave a truncated archive
+#[cfg(feature = "compression")]
+fn compress(path: &Path, compression: PyCompression) -> io::Result<()> {
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".");
+    compressed.push(compression.extension());
+    let mut tmp = compressed.clone();
+    tmp.push(TMP_EXTENSION);
+
+    let result = (|| {
+        let mut input = File::open(path)?;
+        let output = io::BufWriter::new(File::create(&tmp)?);
+        let mut output = match compression {
+            PyCompression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(output, flate2::Compression::default());
+                io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?
+            }
+            PyCompression::Zstd => {
+                let mut encoder = zstd::Encoder::new(output, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+                io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?
+            }
+        };
+        output.flush()?;
+        fs::rename(&tmp, &compressed)
+    })();
+
+    match result {
+        Ok(()) => fs::remove_file(path),
+        Err(err) => {
+            let _ = fs::remove_file(&tmp);
+            Err(err)
+        }
+    }
+}
+
+// unreachable, RollingLog can't be created with compression if the feature is disabled
+#[cfg(not(feature = "compression"))]
+fn compress(_path: &Path, _compression: PyCompression) -> io::Result<()> {
+    Ok(())
+}
+
+impl Write for RollingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = OffsetDateTime::now_utc();
+        let period_ended = period_start(self.config.rotation, now) != self.period;
+        // a single line is never split between the files, even if it's larger than max_size
+        let file_full = self
+            .config
+            .max_size
+            .is_some_and(|max_size| self.size > 0 && self.size + buf.len() as u64 > max_size);
+        if period_ended || file_full || self.file.is_none() {
+            self.rotate(now)?;
+        }
+
+        let Some(file) = &mut self.file else {
+            return Err(io::ErrorKind::NotFound.into());
+        };
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn period_start(rotation: PyRotation, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    let start = match rotation {
+        PyRotation::Minutely => now.replace_time(Time::from_hms(now.hour(), now.minute(), 0).ok()?),
+        PyRotation::Hourly => now.replace_time(Time::from_hms(now.hour(), 0, 0).ok()?),
+        PyRotation::Daily => now.replace_time(Time::MIDNIGHT),
+        // weeks start on sunday, like in tracing-appender
+        PyRotation::Weekly => {
+            let days = now.weekday().number_days_from_sunday();
+            now.replace_time(Time::MIDNIGHT) - Duration::days(days.into())
+        }
+        PyRotation::Never => return None,
+    };
+    Some(start)
+}
+
+fn format_period(rotation: PyRotation, period: OffsetDateTime) -> Option<String> {
+    let format = match rotation {
+        PyRotation::Minutely => MINUTELY,
+        PyRotation::Hourly => HOURLY,
+        PyRotation::Daily | PyRotation::Weekly => DAILY,
+        PyRotation::Never => return None,
+    };
+    period.format(format).ok()
+}
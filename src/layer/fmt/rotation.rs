@@ -18,6 +18,22 @@ impl PyRotation {
     }
 }
 
+#[pyclass(name = "Compression", rename_all = "UPPERCASE", from_py_object)]
+#[derive(Clone, Copy)]
+pub enum PyCompression {
+    Gzip,
+    Zstd,
+}
+
+impl PyCompression {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+}
+
 impl From<PyRotation> for Rotation {
     fn from(value: PyRotation) -> Self {
         match value {
@@ -0,0 +1,387 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+#[derive(Clone)]
+enum SocketAddress {
+    #[cfg(unix)]
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl SocketAddress {
+    // same as SyslogLayer addresses, except that tcp needs a port
+    fn parse(address: &str) -> PyResult<Self> {
+        if let Some(host) = address.strip_prefix("tcp://") {
+            if !host
+                .rsplit_once(':')
+                .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+            {
+                return Err(PyValueError::new_err(format!(
+                    "expected tcp://host:port, got {address}"
+                )));
+            }
+            Ok(Self::Tcp(host.to_owned()))
+        } else {
+            Self::unix(address.strip_prefix("unix://").unwrap_or(address))
+        }
+    }
+
+    #[cfg(unix)]
+    #[expect(clippy::unnecessary_wraps, reason = "it's an error on other platforms")]
+    fn unix(path: &str) -> PyResult<Self> {
+        Ok(Self::Unix(PathBuf::from(path)))
+    }
+
+    #[cfg(not(unix))]
+    fn unix(path: &str) -> PyResult<Self> {
+        Err(PyValueError::new_err(format!(
+            "unix sockets are not supported on this platform, expected tcp://host:port, got {path}"
+        )))
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(
+    not(feature = "tls"),
+    expect(dead_code, reason = "SocketLog can't be created with tls")
+)]
+struct TlsOptions {
+    server_name: String,
+    // the webpki roots are used if it's not set
+    ca_file: Option<String>,
+}
+
+// newline-delimited lines over a stream socket, for the log shippers that listen on one (vector, fluent bit)
+#[pyclass(name = "SocketLog", from_py_object)]
+#[derive(Clone)]
+pub struct PySocketLog {
+    address: SocketAddress,
+    #[cfg_attr(not(feature = "tls"), expect(dead_code, reason = "it's always None"))]
+    tls: Option<TlsOptions>,
+    // in bytes, the oldest lines are dropped when the buffer is full
+    buffer_size: usize,
+    timeout: Duration,
+    reconnect_delay: Duration,
+    max_reconnect_delay: Duration,
+}
+
+#[pymethods]
+impl PySocketLog {
+    #[expect(
+        clippy::too_many_arguments,
+        reason = "same as FmtLayer, it's a python constructor"
+    )]
+    #[new]
+    #[pyo3(signature = (
+        address,
+        *,
+        tls = false,
+        server_name = None,
+        ca_file = None,
+        buffer_size = 8 * 1024 * 1024,
+        timeout = 5.0,
+        reconnect_delay = 0.1,
+        max_reconnect_delay = 30.0,
+    ))]
+    fn new(
+        address: &str,
+        tls: bool,
+        server_name: Option<String>,
+        ca_file: Option<String>,
+        buffer_size: usize,
+        timeout: f64,
+        reconnect_delay: f64,
+        max_reconnect_delay: f64,
+    ) -> PyResult<Self> {
+        let address = SocketAddress::parse(address)?;
+        let duration = |name, secs| {
+            Duration::try_from_secs_f64(secs)
+                .map_err(|e| PyValueError::new_err(format!("invalid {name}: {e}")))
+        };
+        let timeout = duration("timeout", timeout)?;
+        let reconnect_delay = duration("reconnect_delay", reconnect_delay)?;
+        let max_reconnect_delay = duration("max_reconnect_delay", max_reconnect_delay)?;
+
+        if buffer_size == 0 {
+            return Err(PyValueError::new_err("buffer_size must be greater than 0"));
+        }
+        // a zero timeout is an error for the socket options
+        if timeout.is_zero() {
+            return Err(PyValueError::new_err("timeout must be greater than 0"));
+        }
+        if max_reconnect_delay < reconnect_delay {
+            return Err(PyValueError::new_err(
+                "max_reconnect_delay must not be less than reconnect_delay",
+            ));
+        }
+        if !tls && (server_name.is_some() || ca_file.is_some()) {
+            return Err(PyValueError::new_err(
+                "server_name and ca_file require tls=True",
+            ));
+        }
+
+        let tls = if tls {
+            if cfg!(not(feature = "tls")) {
+                return Err(PyValueError::new_err(
+                    "tracing was built without the tls feature",
+                ));
+            }
+            let SocketAddress::Tcp(host) = &address else {
+                return Err(PyValueError::new_err("tls requires a tcp:// address"));
+            };
+            // the host without the port and the ipv6 brackets
+            let server_name = server_name.unwrap_or_else(|| {
+                let (host, _) = host.rsplit_once(':').unwrap_or((host, ""));
+                host.trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_owned()
+            });
+            Some(TlsOptions {
+                server_name,
+                ca_file,
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            address,
+            tls,
+            buffer_size,
+            timeout,
+            reconnect_delay,
+            max_reconnect_delay,
+        })
+    }
+}
+
+enum Stream {
+    #[cfg(unix)]
+    Unix(UnixStream),
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Self::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+// the lines are queued and sent when the socket is connected; if it's not, a reconnect is attempted
+// once the backoff delay has passed, so a missing listener doesn't make every event wait for a timeout
+pub struct SocketWriter {
+    config: PySocketLog,
+    #[cfg(feature = "tls")]
+    tls_config: Option<Arc<rustls::ClientConfig>>,
+    stream: Option<Stream>,
+    pending: VecDeque<Vec<u8>>,
+    pending_bytes: usize,
+    next_attempt: Instant,
+    delay: Duration,
+}
+
+impl SocketWriter {
+    pub fn new(config: PySocketLog) -> io::Result<Self> {
+        // the ca file is read once, so a wrong path is reported by init()
+        #[cfg(feature = "tls")]
+        let tls_config = config
+            .tls
+            .as_ref()
+            .map(|tls| tls_config(tls.ca_file.as_deref()))
+            .transpose()?;
+        Ok(Self {
+            delay: config.reconnect_delay,
+            config,
+            #[cfg(feature = "tls")]
+            tls_config,
+            stream: None,
+            pending: VecDeque::new(),
+            pending_bytes: 0,
+            next_attempt: Instant::now(),
+        })
+    }
+
+    fn connect(&self) -> io::Result<Stream> {
+        let timeout = self.config.timeout;
+        Ok(match &self.config.address {
+            #[cfg(unix)]
+            SocketAddress::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_write_timeout(Some(timeout))?;
+                Stream::Unix(stream)
+            }
+            SocketAddress::Tcp(host) => {
+                let mut last_err = None;
+                let mut connected = None;
+                for addr in host.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(stream) => {
+                            connected = Some(stream);
+                            break;
+                        }
+                        Err(err) => last_err = Some(err),
+                    }
+                }
+                let stream = connected.ok_or_else(|| {
+                    last_err.unwrap_or_else(|| io::Error::other(format!("{host} didn't resolve")))
+                })?;
+                stream.set_write_timeout(Some(timeout))?;
+                stream.set_nodelay(true)?;
+                self.wrap_tls(stream)?
+            }
+        })
+    }
+
+    #[cfg(feature = "tls")]
+    fn wrap_tls(&self, stream: TcpStream) -> io::Result<Stream> {
+        let (Some(tls), Some(tls_config)) = (&self.config.tls, &self.tls_config) else {
+            return Ok(Stream::Tcp(stream));
+        };
+        let server_name = rustls::pki_types::ServerName::try_from(tls.server_name.clone())
+            .map_err(io::Error::other)?;
+        let connection = rustls::ClientConnection::new(tls_config.clone(), server_name)
+            .map_err(io::Error::other)?;
+        Ok(Stream::Tls(Box::new(rustls::StreamOwned::new(
+            connection, stream,
+        ))))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    #[expect(
+        clippy::unnecessary_wraps,
+        reason = "it's fallible with the tls feature"
+    )]
+    fn wrap_tls(&self, stream: TcpStream) -> io::Result<Stream> {
+        Ok(Stream::Tcp(stream))
+    }
+
+    fn push(&mut self, line: &[u8]) {
+        // the oldest lines are dropped, a line larger than the buffer is still sent
+        while !self.pending.is_empty() && self.pending_bytes + line.len() > self.config.buffer_size
+        {
+            if let Some(dropped) = self.pending.pop_front() {
+                self.pending_bytes -= dropped.len();
+            }
+        }
+        self.pending_bytes += line.len();
+        self.pending.push_back(line.to_vec());
+    }
+
+    // connection errors are not reported, the lines stay in the buffer until the listener is back
+    fn send(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        if self.stream.is_none() {
+            let now = Instant::now();
+            if now < self.next_attempt {
+                return;
+            }
+            match self.connect() {
+                Ok(stream) => {
+                    self.stream = Some(stream);
+                    self.delay = self.config.reconnect_delay;
+                }
+                Err(_) => {
+                    self.next_attempt = now + self.delay;
+                    self.delay = (self.delay * 2).min(self.config.max_reconnect_delay);
+                    return;
+                }
+            }
+        }
+
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+        // a line that wasn't written completely is sent again on the new connection
+        let result = (|| {
+            while let Some(line) = self.pending.front() {
+                stream.write_all(line)?;
+                self.pending_bytes -= line.len();
+                self.pending.pop_front();
+            }
+            stream.flush()
+        })();
+        if result.is_err() {
+            // the connection was probably closed by the listener, reconnecting right away
+            self.stream = None;
+            self.next_attempt = Instant::now();
+        }
+    }
+}
+
+// it's only used by the non-blocking worker, which writes everything that's queued before flushing,
+// so the lines are sent on flush
+impl Write for SocketWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.push(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send();
+        Ok(())
+    }
+}
+
+// the worker drops the writer at shutdown, the queued lines get one more attempt even if the backoff
+// delay hasn't passed; the second one reconnects if the listener has closed the connection
+impl Drop for SocketWriter {
+    fn drop(&mut self) {
+        self.next_attempt = Instant::now();
+        self.send();
+        self.send();
+    }
+}
+
+#[cfg(feature = "tls")]
+fn tls_config(ca_file: Option<&str>) -> io::Result<Arc<rustls::ClientConfig>> {
+    use rustls::pki_types::{CertificateDer, pem::PemObject};
+
+    let mut roots = rustls::RootCertStore::empty();
+    match ca_file {
+        Some(path) => {
+            for cert in CertificateDer::pem_file_iter(path).map_err(io::Error::other)? {
+                roots
+                    .add(cert.map_err(io::Error::other)?)
+                    .map_err(io::Error::other)?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    Ok(Arc::new(
+        rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    ))
+}
@@ -1,46 +1,53 @@
-use std::{
-    fs::OpenOptions,
-    io::{stderr, stdout},
-};
+use std::io::{stderr, stdout};
 
-use pyo3::{Bound, PyResult, exceptions::PyRuntimeError};
-use time::UtcOffset;
-use tracing::Level;
-use tracing_appender::{
-    non_blocking::{NonBlockingBuilder, WorkerGuard},
-    rolling::RollingFileAppender,
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
 };
-use tracing_core::LevelFilter;
+use time::UtcOffset;
 use tracing_subscriber::{
     Layer, Registry,
     fmt::{
         self, FormatEvent, FormatFields, MakeWriter,
-        format::{self, DefaultFields, Format},
-        time::{FormatTime, OffsetTime, Uptime, UtcTime},
+        format::{self, DefaultFields, FmtSpan, Format},
+        time::{FormatTime, OffsetTime, SystemTime, Uptime, UtcTime},
     },
 };
 
-use crate::layer::{
-    ThreadSafeLayer,
-    fmt::{
-        FmtLayer, LogFile, PyFormat,
-        file::NonBlocking,
-        time::{
-            format::TimeFormat,
-            formats::{ISO8601, ISO8601_NO_SUBSECONDS},
-            timer::{PyTimer, Time, Timer},
+use crate::{
+    imports::get_socket_gethostname,
+    layer::{
+        LayerGuard, LayerWithGuard, ThreadSafeLayer,
+        filter::ReloadLayerFilter,
+        fmt::{
+            FmtLayer, LogFile, LogFormat, PyFormat,
+            ecs::EcsFormat,
+            event::{CustomFormat, EventFormat, LayerFormat},
+            file::NonBlocking,
+            logfmt::LogfmtFields,
+            non_blocking::non_blocking,
+            py_file::PyFile,
+            reopen::ReopenableFile,
+            rolling::RollingWriter,
+            socket::SocketWriter,
+            time::{
+                format::TimeFormat,
+                formats::{ISO8601, ISO8601_NO_SUBSECONDS},
+                timer::{PyTimer, Time, Timer},
+            },
         },
+        fork::ForkSafeMutex,
     },
 };
 
 pub trait ToDynLayer {
-    fn dyn_layer(&self) -> PyResult<(Box<dyn ThreadSafeLayer>, Option<WorkerGuard>)>;
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard>;
 }
 
 impl ToDynLayer for Bound<'_, FmtLayer> {
-    fn dyn_layer(&self) -> PyResult<(Box<dyn ThreadSafeLayer>, Option<WorkerGuard>)> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
         let FmtLayer {
-            log_level,
+            filter,
             file,
             format,
             fmt_span,
@@ -86,15 +93,50 @@ impl ToDynLayer for Bound<'_, FmtLayer> {
             layer = layer.with_thread_ids(*with_thread_ids);
         }
 
-        layer = layer.with_span_events(fmt_span.clone());
+        // the tree is drawn from the span events
+        layer = layer.with_span_events(match format {
+            LogFormat::Predefined(PyFormat::Tree) => FmtSpan::NEW | FmtSpan::CLOSE,
+            _ => fmt_span.clone(),
+        });
+
+        // the formats that tracing doesn't have don't use its Format, so they get the options separately
+        let custom = |format| {
+            LayerFormat::Custom(EventFormat::new(
+                format,
+                *with_level,
+                *with_target,
+                *with_file,
+                *with_line_number,
+                *with_thread_ids,
+            ))
+        };
+        let format = match format {
+            LogFormat::Predefined(PyFormat::Full) => LayerFormat::Full,
+            LogFormat::Predefined(PyFormat::Compact) => LayerFormat::Compact,
+            LogFormat::Predefined(PyFormat::Pretty) => LayerFormat::Pretty,
+            LogFormat::Predefined(PyFormat::Json) => LayerFormat::Json,
+            LogFormat::Predefined(PyFormat::Logfmt) => custom(CustomFormat::Logfmt),
+            LogFormat::Predefined(PyFormat::Tree) => custom(CustomFormat::Tree),
+            // gelf messages must have a host, it's resolved once
+            LogFormat::Predefined(PyFormat::Gelf) => custom(CustomFormat::Gelf(
+                get_socket_gethostname(self.py()).call0()?.extract()?,
+            )),
+            // it's converted to EcsFormat when it's extracted, but the default is the same anyway
+            LogFormat::Predefined(PyFormat::Ecs) => custom(CustomFormat::Ecs(EcsFormat::default())),
+            LogFormat::Json(options) => custom(CustomFormat::Json(options.clone())),
+            LogFormat::Ecs(options) => custom(CustomFormat::Ecs(options.clone())),
+            LogFormat::Line(template) => custom(CustomFormat::Line(template.clone())),
+        };
 
         set_writer_and_rest(
             layer,
-            *log_level,
-            *format,
+            filter.layer()?,
+            format,
             timer.as_ref(),
             file,
-            *non_blocking,
+            non_blocking.as_ref(),
+            // tracing's default
+            log_internal_errors.unwrap_or(true),
         )
     }
 }
@@ -106,9 +148,9 @@ trait FieldFmt = Send + Sync + 'static + for<'a> FormatFields<'a>;
 trait LogFmt = Send + Sync + 'static;
 
 // please help me
-fn set_level_and_finish<F, L, T, W>(
+fn set_filter_and_finish<F, L, T, W>(
     layer: RFmtLayer<F, L, T, W>,
-    level: Level,
+    filter: ReloadLayerFilter,
 ) -> Box<dyn ThreadSafeLayer>
 where
     Format<L, T>: FormatEvent<Registry, F>,
@@ -117,19 +159,14 @@ where
     T: FormatTime + Send + Sync + 'static,
     W: Writer,
 {
-    // no need to use a filter that filters nothing
-    if level == Level::TRACE {
-        Box::new(layer)
-    } else {
-        Box::new(layer.with_filter(LevelFilter::from(level)))
-    }
+    Box::new(layer.with_filter(filter))
 }
 
 // ...please
 fn set_format_and_rest<F, L, T, W>(
     layer: RFmtLayer<F, L, T, W>,
-    level: Level,
-    format: PyFormat,
+    filter: ReloadLayerFilter,
+    format: LayerFormat<T>,
 ) -> Box<dyn ThreadSafeLayer>
 where
     Format<L, T>: FormatEvent<Registry, F>,
@@ -139,24 +176,57 @@ where
     W: Writer,
 {
     match format {
-        PyFormat::Full => set_level_and_finish(layer, level),
-        PyFormat::Compact => {
-            set_level_and_finish::<F, format::Compact, T, W>(layer.compact(), level)
+        LayerFormat::Full => set_filter_and_finish(layer, filter),
+        LayerFormat::Compact => {
+            set_filter_and_finish::<F, format::Compact, T, W>(layer.compact(), filter)
         }
-        PyFormat::Pretty => {
-            set_level_and_finish::<format::Pretty, format::Pretty, T, W>(layer.pretty(), level)
+        LayerFormat::Pretty => {
+            set_filter_and_finish::<format::Pretty, format::Pretty, T, W>(layer.pretty(), filter)
         }
-        PyFormat::Json => {
-            set_level_and_finish::<format::JsonFields, format::Json, T, W>(layer.json(), level)
+        LayerFormat::Json => {
+            set_filter_and_finish::<format::JsonFields, format::Json, T, W>(layer.json(), filter)
         }
+        LayerFormat::Custom(format) => match format.format {
+            CustomFormat::Tree => Box::new(layer.event_format(format).with_filter(filter)),
+            CustomFormat::Logfmt => Box::new(
+                layer
+                    .fmt_fields(LogfmtFields)
+                    .event_format(format)
+                    .with_filter(filter),
+            ),
+            CustomFormat::Json(_)
+            | CustomFormat::Ecs(_)
+            | CustomFormat::Line(_)
+            | CustomFormat::Gelf(_) => Box::new(
+                layer
+                    .fmt_fields(format::JsonFields::new())
+                    .event_format(format)
+                    .with_filter(filter),
+            ),
+        },
     }
 }
 
+// the timer is needed twice, for tracing's Format and for EventFormat
+fn set_timer_and_format<T, W>(
+    layer: fmt::Layer<Registry, DefaultFields, Format<format::Full>, W>,
+    timer: T,
+    filter: ReloadLayerFilter,
+    format: LayerFormat,
+) -> Box<dyn ThreadSafeLayer>
+where
+    T: TimeFmt + Clone,
+    W: Writer,
+{
+    let format = format.with_timer(timer.clone());
+    set_format_and_rest(layer.with_timer(timer), filter, format)
+}
+
 // this is literally typeslop, who thought using types to parametrize your structs is a good idea
 fn set_timer_and_rest<W>(
     layer: fmt::Layer<Registry, DefaultFields, Format<format::Full>, W>,
-    level: Level,
-    format: PyFormat,
+    filter: ReloadLayerFilter,
+    format: LayerFormat,
     timer: Option<&PyTimer>,
 ) -> PyResult<Box<dyn ThreadSafeLayer>>
 where
@@ -164,28 +234,26 @@ where
 {
     Ok(match timer {
         Some(fmt) => match fmt.timer() {
-            Timer::SystemTime => set_format_and_rest(layer, level, format),
-            Timer::Uptime => {
-                set_format_and_rest(layer.with_timer(Uptime::default()), level, format)
-            }
+            Timer::SystemTime => set_format_and_rest(layer, filter, format.with_timer(SystemTime)),
+            Timer::Uptime => set_timer_and_format(layer, Uptime::default(), filter, format),
             Timer::Custom(time, time_format) => match time {
                 Time::Utc => match &time_format {
-                    TimeFormat::Custom(owned_format_item) => set_format_and_rest(
-                        layer.with_timer(UtcTime::new(owned_format_item.clone())),
-                        level,
-                        format,
-                    ),
-                    TimeFormat::Predefined(predefined) => set_format_and_rest(
-                        layer.with_timer(UtcTime::new(*predefined)),
-                        level,
+                    TimeFormat::Custom(owned_format_item) => set_timer_and_format(
+                        layer,
+                        UtcTime::new(owned_format_item.clone()),
+                        filter,
                         format,
                     ),
+                    TimeFormat::Predefined(predefined) => {
+                        set_timer_and_format(layer, UtcTime::new(*predefined), filter, format)
+                    }
                     TimeFormat::Iso8601 => {
-                        set_format_and_rest(layer.with_timer(UtcTime::new(ISO8601)), level, format)
+                        set_timer_and_format(layer, UtcTime::new(ISO8601), filter, format)
                     }
-                    TimeFormat::Iso8601NoSubseconds => set_format_and_rest(
-                        layer.with_timer(UtcTime::new(ISO8601_NO_SUBSECONDS)),
-                        level,
+                    TimeFormat::Iso8601NoSubseconds => set_timer_and_format(
+                        layer,
+                        UtcTime::new(ISO8601_NO_SUBSECONDS),
+                        filter,
                         format,
                     ),
                 },
@@ -193,31 +261,35 @@ where
                     let offset = UtcOffset::current_local_offset()
                         .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
                     match time_format {
-                        TimeFormat::Custom(owned_format_item) => set_format_and_rest(
-                            layer.with_timer(OffsetTime::new(offset, owned_format_item.clone())),
-                            level,
+                        TimeFormat::Custom(owned_format_item) => set_timer_and_format(
+                            layer,
+                            OffsetTime::new(offset, owned_format_item.clone()),
+                            filter,
                             format,
                         ),
-                        TimeFormat::Predefined(predefined) => set_format_and_rest(
-                            layer.with_timer(OffsetTime::new(offset, *predefined)),
-                            level,
+                        TimeFormat::Predefined(predefined) => set_timer_and_format(
+                            layer,
+                            OffsetTime::new(offset, *predefined),
+                            filter,
                             format,
                         ),
-                        TimeFormat::Iso8601 => set_format_and_rest(
-                            layer.with_timer(OffsetTime::new(offset, ISO8601)),
-                            level,
+                        TimeFormat::Iso8601 => set_timer_and_format(
+                            layer,
+                            OffsetTime::new(offset, ISO8601),
+                            filter,
                             format,
                         ),
-                        TimeFormat::Iso8601NoSubseconds => set_format_and_rest(
-                            layer.with_timer(OffsetTime::new(offset, ISO8601_NO_SUBSECONDS)),
-                            level,
+                        TimeFormat::Iso8601NoSubseconds => set_timer_and_format(
+                            layer,
+                            OffsetTime::new(offset, ISO8601_NO_SUBSECONDS),
+                            filter,
                             format,
                         ),
                     }
                 }
             },
         },
-        None => set_format_and_rest(layer.without_time(), level, format),
+        None => set_format_and_rest(layer.without_time(), filter, format),
     })
 }
 
@@ -225,57 +297,73 @@ where
 // mainly because all used types must be present during the compile time, yes
 fn set_writer_and_rest(
     layer: fmt::Layer<Registry>,
-    level: Level,
-    format: PyFormat,
+    filter: ReloadLayerFilter,
+    format: LayerFormat,
     timer: Option<&PyTimer>,
     file: &LogFile,
-    nonblocking: Option<NonBlocking>,
-) -> PyResult<(Box<dyn ThreadSafeLayer>, Option<WorkerGuard>)>
-where
-{
-    let mut opts = OpenOptions::new();
-    let opts = opts.create(true).append(true);
-
+    nonblocking: Option<&NonBlocking>,
+    log_internal_errors: bool,
+) -> PyResult<LayerWithGuard> {
     Ok(if let Some(nonblocking) = nonblocking {
-        let builder = NonBlockingBuilder::default();
-        let builder = match nonblocking {
-            NonBlocking::Lossy => builder.lossy(true),
-            NonBlocking::Complete => builder.lossy(false),
-        };
-
+        let mut compression_guard = None;
         let (writer, guard) = match file {
-            LogFile::Stdout => builder.finish(stdout()),
-            LogFile::Stderr => builder.finish(stderr()),
-            LogFile::Path(path) => {
-                let file = opts.open(path)?;
-                builder.finish(file)
+            LogFile::Stdout => non_blocking(stdout(), nonblocking, log_internal_errors),
+            LogFile::Stderr => non_blocking(stderr(), nonblocking, log_internal_errors),
+            LogFile::Path(path) => non_blocking(
+                ReopenableFile::open(path)?,
+                nonblocking,
+                log_internal_errors,
+            ),
+            LogFile::Rolling(rolling) => {
+                let rolling = RollingWriter::new(rolling.clone(), log_internal_errors)?;
+                compression_guard = rolling.compression_guard();
+                non_blocking(rolling, nonblocking, log_internal_errors)
             }
-            LogFile::Rolling(rolling) => builder.finish(RollingFileAppender::new(
-                rolling.rotation.clone(),
-                rolling.dir.clone(),
-                rolling.prefix.clone(),
-            )),
+            LogFile::Socket(socket) => non_blocking(
+                SocketWriter::new(socket.clone())?,
+                nonblocking,
+                log_internal_errors,
+            ),
+            LogFile::Python(object) => non_blocking(
+                PyFile::new(object.clone(), true),
+                nonblocking,
+                log_internal_errors,
+            ),
+        }?;
+        let layer = set_timer_and_rest(layer.with_writer(writer), filter, format, timer);
+        let guard: LayerGuard = match compression_guard {
+            Some(compression_guard) => Box::new((guard, compression_guard)),
+            None => Box::new(guard),
         };
-        let layer = set_timer_and_rest(layer.with_writer(writer), level, format, timer);
         (layer?, Some(guard))
     } else {
+        let mut compression_guard = None;
         let layer = match file {
-            LogFile::Stdout => set_timer_and_rest(layer.with_writer(stdout), level, format, timer),
-            LogFile::Stderr => set_timer_and_rest(layer.with_writer(stderr), level, format, timer),
+            LogFile::Stdout => set_timer_and_rest(layer.with_writer(stdout), filter, format, timer),
+            LogFile::Stderr => set_timer_and_rest(layer.with_writer(stderr), filter, format, timer),
             LogFile::Path(path) => {
-                let file = opts.open(path)?;
-                set_timer_and_rest(layer.with_writer(file), level, format, timer)
+                let file = ReopenableFile::open(path)?;
+                set_timer_and_rest(layer.with_writer(file), filter, format, timer)
             }
             LogFile::Rolling(rolling) => {
-                let rolling = RollingFileAppender::new(
-                    rolling.rotation.clone(),
-                    rolling.dir.clone(),
-                    rolling.prefix.clone(),
-                );
-                set_timer_and_rest(layer.with_writer(rolling), level, format, timer)
+                let rolling = RollingWriter::new(rolling.clone(), log_internal_errors)?;
+                compression_guard = rolling.compression_guard();
+                let rolling = ForkSafeMutex::new(rolling);
+                set_timer_and_rest(layer.with_writer(rolling), filter, format, timer)
+            }
+            // connecting and writing would block the thread that logs, with the gil held
+            LogFile::Socket(_) => {
+                return Err(PyValueError::new_err("SocketLog requires non_blocking"));
+            }
+            LogFile::Python(object) => {
+                let file = PyFile::new(object.clone(), false);
+                set_timer_and_rest(layer.with_writer(file), filter, format, timer)
             }
         };
-        (layer?, None)
+        (
+            layer?,
+            compression_guard.map(|guard| Box::new(guard) as LayerGuard),
+        )
     })
 }
 // todo: rewrite functions above using macros
@@ -0,0 +1,138 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tracing::{Event, span};
+use tracing_subscriber::{
+    Registry,
+    fmt::{FmtContext, FormatFields, FormattedFields, format::Writer, time::FormatTime},
+    registry::SpanRef,
+};
+
+use crate::layer::{fields::TextVisitor, fmt::event::EventFormat};
+
+static NEXT_TREE_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // the span the last line of this thread was written in, by tree id; when the next line of the thread is in
+    // another one, e.g. after a coroutine was resumed, that span is written again, so it's clear where the line
+    // belongs. the entries are removed when the thread leaves its outermost span, and dropped with the thread
+    static LAST_SPANS: RefCell<HashMap<u64, span::Id>> = RefCell::default();
+}
+
+// each tree layer draws its own lines, so it keeps its own last spans
+pub struct TreeContext(u64);
+
+impl Default for TreeContext {
+    fn default() -> Self {
+        Self(NEXT_TREE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// ┌─ handle request_id=1
+// │  INFO user logged in user_id=42
+// │  ┌─ query
+// │  └─ query busy=1.20ms idle=3.10µs
+// └─ handle busy=2.40ms idle=7.00µs
+//
+// the spans are opened and closed by the span events, FmtLayer enables them for this format
+pub fn format_event<T, N>(
+    format: &EventFormat<T>,
+    ctx: &FmtContext<'_, Registry, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+) -> fmt::Result
+where
+    T: FormatTime,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    let meta = event.metadata();
+    let ts = format
+        .timestamp()
+        .map(|ts| format!("{ts} "))
+        .unwrap_or_default();
+    let spans = ctx
+        .event_scope()
+        .map(|scope| scope.from_root().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let mut visitor = TextVisitor::default();
+    event.record(&mut visitor);
+    // the span events have the span metadata
+    let span_event = meta
+        .is_span()
+        .then_some(visitor.message.as_deref())
+        .flatten();
+
+    let tree = format.tree.0;
+    let last = LAST_SPANS.with_borrow(|spans| spans.get(&tree).cloned());
+    let mut out = String::new();
+    match (span_event, spans.split_last()) {
+        (Some("new"), Some((span, parents))) => {
+            retrace(&mut out, &ts, parents, &last);
+            let fields = span
+                .extensions()
+                .get::<FormattedFields<N>>()
+                .map(|fields| fields.fields.clone())
+                .unwrap_or_default();
+            push_line(&mut out, &ts, parents.len(), "┌─ ", span.name(), &fields);
+            set_last(tree, Some(span));
+        }
+        (Some("close"), Some((span, parents))) => {
+            retrace(&mut out, &ts, &spans, &last);
+            let timings = visitor
+                .fields
+                .iter()
+                .map(|(name, value)| format!("{}={value}", name.trim_start_matches("time.")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            push_line(&mut out, &ts, parents.len(), "└─ ", span.name(), &timings);
+            set_last(tree, parents.last());
+        }
+        // the other span events are not enabled
+        (Some(_), _) => return Ok(()),
+        (None, _) => {
+            retrace(&mut out, &ts, &spans, &last);
+            let mut fields = String::new();
+            ctx.format_fields(Writer::new(&mut fields), event)?;
+            if format.with_level {
+                let level = format!("{:>5}", meta.level().as_str());
+                push_line(&mut out, &ts, spans.len(), "", &level, &fields);
+            } else {
+                push_line(&mut out, &ts, spans.len(), "", &fields, "");
+            }
+            set_last(tree, spans.last());
+        }
+    }
+    write!(writer, "{out}")
+}
+
+fn set_last(tree: u64, span: Option<&SpanRef<'_, Registry>>) {
+    LAST_SPANS.with_borrow_mut(|spans| match span {
+        Some(span) => spans.insert(tree, span.id()),
+        None => spans.remove(&tree),
+    });
+}
+
+fn retrace(out: &mut String, ts: &str, spans: &[SpanRef<'_, Registry>], last: &Option<span::Id>) {
+    if let Some((span, parents)) = spans.split_last()
+        && last.as_ref() != Some(&span.id())
+    {
+        push_line(out, ts, parents.len(), "├┄ ", span.name(), "");
+    }
+}
+
+fn push_line(out: &mut String, ts: &str, depth: usize, branch: &str, head: &str, rest: &str) {
+    out.push_str(ts);
+    out.push_str(&"│  ".repeat(depth));
+    out.push_str(branch);
+    out.push_str(head);
+    if !rest.is_empty() {
+        out.push(' ');
+        out.push_str(rest);
+    }
+    out.push('\n');
+}
@@ -0,0 +1,190 @@
+use std::{
+    io::{self, Write},
+    ops::{Deref, DerefMut},
+    sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard},
+};
+
+#[cfg(unix)]
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    sync::RwLockWriteGuard,
+    thread,
+};
+
+#[cfg(unix)]
+use pyo3::{
+    prelude::*,
+    types::{PyCFunction, PyDict},
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+#[cfg(unix)]
+use crate::{
+    callsite,
+    imports::get_os_register_at_fork,
+    layer::{GUARDS, LayerGuard, filter, fmt::reopen},
+    leak,
+};
+
+// the mutexes of the layers are locked while this one is locked for reading, and the thread that forks
+// locks it for writing, so the child doesn't inherit a writer, a connection or a filter locked by a thread
+// that doesn't exist there; it's never locked for writing otherwise
+static LAYER_LOCKS: RwLock<()> = RwLock::new(());
+
+// the thread that forks already holds it, the other fork hooks may log something
+pub fn lock_layers() -> Option<RwLockReadGuard<'static, ()>> {
+    #[cfg(unix)]
+    if FORKING.get() {
+        return None;
+    }
+    Some(LAYER_LOCKS.read().unwrap_or_else(PoisonError::into_inner))
+}
+
+// a Mutex that isn't held by any thread while the process forks; like tracing-subscriber's Mutex<W> writer,
+// a panic while it's locked doesn't make it unusable
+pub struct ForkSafeMutex<T>(Mutex<T>);
+
+impl<T> ForkSafeMutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self(Mutex::new(value))
+    }
+
+    pub fn lock(&self) -> ForkSafeGuard<'_, T> {
+        let fork = lock_layers();
+        ForkSafeGuard {
+            guard: self.0.lock().unwrap_or_else(PoisonError::into_inner),
+            _fork: fork,
+        }
+    }
+}
+
+impl<T: Default> Default for ForkSafeMutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+// the fields are dropped in order, the mutex is unlocked first
+pub struct ForkSafeGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    _fork: Option<RwLockReadGuard<'static, ()>>,
+}
+
+impl<T> Deref for ForkSafeGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for ForkSafeGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<W: Write> Write for ForkSafeGuard<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.guard.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.guard.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.guard.flush()
+    }
+}
+
+impl<'a, W: Write + 'a> MakeWriter<'a> for ForkSafeMutex<W> {
+    type Writer = ForkSafeGuard<'a, W>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.lock()
+    }
+}
+
+#[cfg(unix)]
+struct ForkLocks {
+    guards: MutexGuard<'static, Vec<LayerGuard>>,
+    _layers: RwLockWriteGuard<'static, ()>,
+    _installed: Box<dyn Any>,
+    _files: Box<dyn Any>,
+    _callsites: Box<dyn Any>,
+    _leaked: Box<dyn Any>,
+}
+
+#[cfg(unix)]
+thread_local! {
+    // os.fork() calls all hooks in the thread that forks
+    static FORK_LOCKS: RefCell<Option<ForkLocks>> = const { RefCell::new(None) };
+    static FORKING: Cell<bool> = const { Cell::new(false) };
+}
+
+// the child only has the thread that called fork(): the non-blocking workers are restarted in it,
+// and the locks are held during the fork, so it doesn't inherit the ones held by the other threads.
+// these are: the guards, the blocking writers (files, rolling files, sockets), the syslog connections,
+// the chrome and flame writers, the filters and the list of the installed ones, the list of the reopenable
+// files, the callsites and the leaked strings. journald sends each message with a single syscall, without a lock.
+// the locks of tracing itself (the reload lock of the layer stack, the span registry, the callsite registry)
+// can't be taken here; the python threads hold them only briefly, but e.g. the otlp exporter threads
+// may log through them too
+#[cfg(unix)]
+pub fn register_hooks(py: Python<'_>) -> PyResult<()> {
+    let before = PyCFunction::new_closure(py, None, None, |args, _| before_fork(args.py()))?;
+    let after = PyCFunction::new_closure(py, None, None, |_, _| after_fork())?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("before", before)?;
+    kwargs.set_item("after_in_parent", &after)?;
+    kwargs.set_item("after_in_child", &after)?;
+    get_os_register_at_fork(py).call((), Some(&kwargs))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn before_fork(py: Python<'_>) {
+    // the workers may need the gil to write the queued lines
+    py.detach(|| GUARDS.lock().unwrap().iter().for_each(|x| x.before_fork()));
+
+    // not blocking with the gil, the threads that hold the locks may be waiting for it
+    let locks = loop {
+        if let (
+            Ok(guards),
+            Ok(layers),
+            Some(installed),
+            Some(files),
+            Some(callsites),
+            Some(leaked),
+        ) = (
+            GUARDS.try_lock(),
+            LAYER_LOCKS.try_write(),
+            filter::try_lock_for_fork(),
+            reopen::try_lock_for_fork(),
+            callsite::try_lock_for_fork(),
+            leak::try_lock_for_fork(),
+        ) {
+            break ForkLocks {
+                guards,
+                _layers: layers,
+                _installed: Box::new(installed),
+                _files: Box::new(files),
+                _callsites: Box::new(callsites),
+                _leaked: Box::new(leaked),
+            };
+        }
+        py.detach(thread::yield_now);
+    };
+    FORK_LOCKS.set(Some(locks));
+    FORKING.set(true);
+}
+
+#[cfg(unix)]
+fn after_fork() {
+    if let Some(locks) = FORK_LOCKS.take() {
+        locks.guards.iter().for_each(|x| x.after_fork());
+    }
+    FORKING.set(false);
+}
@@ -0,0 +1,318 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, IoSlice, Write},
+    os::{
+        fd::AsRawFd,
+        unix::net::{SocketAncillary, UnixDatagram},
+    },
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use pyo3::prelude::*;
+use tracing::{Event, span};
+use tracing_subscriber::{Layer, Registry, layer::Context};
+
+use crate::{
+    layer::{
+        LayerWithGuard,
+        fields::TextVisitor,
+        filter::{PyEnvFilter, ReloadableFilter, reloadable_filter_methods},
+        fmt::to_layer::ToDynLayer,
+        syslog::{default_app_name, severity},
+    },
+    level::PyLevel,
+};
+
+// EMSGSIZE on linux
+const MESSAGE_TOO_LONG: i32 = 90;
+
+// the fields set from the event, the user fields with these names are prefixed even without field_prefix
+const RESERVED_FIELDS: &[&str] = &[
+    "MESSAGE",
+    "PRIORITY",
+    "SYSLOG_IDENTIFIER",
+    "SYSLOG_PID",
+    "TARGET",
+    "CODE_FILE",
+    "CODE_LINE",
+    "CODE_FUNC",
+];
+
+#[pyclass(name = "JournaldLayer")]
+pub struct PyJournaldLayer {
+    path: PathBuf,
+    syslog_identifier: String,
+    field_prefix: Option<String>,
+    log_internal_errors: bool,
+    filter: ReloadableFilter,
+}
+
+#[pymethods]
+impl PyJournaldLayer {
+    #[new]
+    #[pyo3(signature = (
+        path = "/run/systemd/journal/socket",
+        *,
+        syslog_identifier = None,
+        field_prefix = Some("F".to_owned()),
+        log_level = PyLevel::Info,
+        filter = None,
+        log_internal_errors = true,
+    ))]
+    fn new(
+        py: Python<'_>,
+        path: &str,
+        syslog_identifier: Option<String>,
+        field_prefix: Option<String>,
+        log_level: PyLevel,
+        filter: Option<PyEnvFilter>,
+        log_internal_errors: bool,
+    ) -> PyResult<Self> {
+        let syslog_identifier = match syslog_identifier {
+            Some(syslog_identifier) => syslog_identifier,
+            None => default_app_name(py)?,
+        };
+        Ok(Self {
+            path: PathBuf::from(path),
+            syslog_identifier,
+            field_prefix: field_prefix.map(|prefix| field_name(&prefix)),
+            log_internal_errors,
+            filter: ReloadableFilter::from_level(log_level, filter),
+        })
+    }
+}
+
+reloadable_filter_methods!(PyJournaldLayer, filter);
+
+impl ToDynLayer for Bound<'_, PyJournaldLayer> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
+        let this = self.borrow();
+        let layer = JournaldLayer {
+            socket: UnixDatagram::unbound()?,
+            path: this.path.clone(),
+            syslog_identifier: this.syslog_identifier.clone(),
+            field_prefix: this.field_prefix.clone(),
+            log_internal_errors: this.log_internal_errors,
+            failing: AtomicBool::new(false),
+        };
+        Ok((Box::new(layer.with_filter(this.filter.layer()?)), None))
+    }
+}
+
+// span fields, recorded when the span is created and added to each event inside of it
+struct SpanFields(Vec<(&'static str, String)>);
+
+struct JournaldLayer {
+    // not connected, so the messages are delivered after journald restarts
+    socket: UnixDatagram,
+    path: PathBuf,
+    syslog_identifier: String,
+    field_prefix: Option<String>,
+    log_internal_errors: bool,
+    // only the first error of a series is printed
+    failing: AtomicBool,
+}
+
+impl JournaldLayer {
+    fn user_field(&self, payload: &mut Vec<u8>, name: &str, value: &str) {
+        let name = field_name(name);
+        let name = match &self.field_prefix {
+            Some(prefix) => format!("{prefix}_{name}"),
+            // the names that already start with `F_` are prefixed too, so `message` and `f_message` don't clash
+            None if RESERVED_FIELDS.contains(&name.as_str()) || name.starts_with("F_") => {
+                format!("F_{name}")
+            }
+            None => name,
+        };
+        // the names are ascii, journald rejects the ones longer than 64 characters
+        put_field(payload, &name[..name.len().min(64)], value.as_bytes());
+    }
+
+    fn format(&self, event: &Event<'_>, ctx: &Context<'_, Registry>) -> Vec<u8> {
+        let meta = event.metadata();
+
+        let mut visitor = TextVisitor::default();
+        event.record(&mut visitor);
+
+        let mut payload = Vec::new();
+        put_field(
+            &mut payload,
+            "MESSAGE",
+            visitor.message.unwrap_or_default().as_bytes(),
+        );
+        put_field(
+            &mut payload,
+            "PRIORITY",
+            severity(*meta.level()).to_string().as_bytes(),
+        );
+        put_field(
+            &mut payload,
+            "SYSLOG_IDENTIFIER",
+            self.syslog_identifier.as_bytes(),
+        );
+        put_field(
+            &mut payload,
+            "SYSLOG_PID",
+            process::id().to_string().as_bytes(),
+        );
+        put_field(&mut payload, "TARGET", meta.target().as_bytes());
+        if let Some(file) = meta.file() {
+            put_field(&mut payload, "CODE_FILE", file.as_bytes());
+        }
+        if let Some(line) = meta.line() {
+            put_field(&mut payload, "CODE_LINE", line.to_string().as_bytes());
+        }
+        // the target is the qualified name of the calling function
+        put_field(&mut payload, "CODE_FUNC", meta.target().as_bytes());
+
+        // the outer spans first, a field may be repeated, journald keeps all of the values
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    for (name, value) in &fields.0 {
+                        self.user_field(&mut payload, name, value);
+                    }
+                }
+            }
+        }
+        for (name, value) in &visitor.fields {
+            self.user_field(&mut payload, name, value);
+        }
+        payload
+    }
+
+    fn send(&self, payload: &[u8]) -> io::Result<()> {
+        match self.socket.send_to(payload, &self.path) {
+            Err(err) if err.raw_os_error() == Some(MESSAGE_TOO_LONG) => self.send_large(payload),
+            result => result.map(|_| ()),
+        }
+    }
+
+    // the payload that doesn't fit into a datagram is written to an unlinked file in /dev/shm,
+    // and its descriptor is sent instead, see systemd's JOURNAL_NATIVE_PROTOCOL
+    fn send_large(&self, payload: &[u8]) -> io::Result<()> {
+        let mut file = unlinked_file()?;
+        file.write_all(payload)?;
+
+        let mut buffer = [0; 64];
+        let mut ancillary = SocketAncillary::new(&mut buffer);
+        ancillary.add_fds(&[file.as_raw_fd()]);
+        // the descriptor is sent to the socket path, so the socket is connected only for this message
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&self.path)?;
+        socket.send_vectored_with_ancillary(&[IoSlice::new(&[])], &mut ancillary)?;
+        Ok(())
+    }
+}
+
+fn unlinked_file() -> io::Result<File> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let path = Path::new("/dev/shm").join(format!(
+            "tracing-journald.{}.{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => {
+                fs::remove_file(&path)?;
+                return Ok(file);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+impl Layer<Registry> for JournaldLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, Registry>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = TextVisitor::default();
+        attrs.record(&mut visitor);
+
+        // there may be multiple journald layers, the fields are the same for all of them
+        let mut extensions = span.extensions_mut();
+        if extensions.get_mut::<SpanFields>().is_none() {
+            extensions.insert(SpanFields(span_fields(visitor)));
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, Registry>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(fields) = span.extensions_mut().get_mut::<SpanFields>()
+        {
+            let mut visitor = TextVisitor::default();
+            values.record(&mut visitor);
+            for (name, value) in span_fields(visitor) {
+                match fields.0.iter_mut().find(|(x, _)| *x == name) {
+                    Some((_, old)) => *old = value,
+                    None => fields.0.push((name, value)),
+                }
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        let payload = self.format(event, &ctx);
+        match self.send(&payload) {
+            Ok(()) => self.failing.store(false, Ordering::Relaxed),
+            Err(err) => {
+                if !self.failing.swap(true, Ordering::Relaxed) && self.log_internal_errors {
+                    eprintln!(
+                        "tracing: failed to send a journald message, the messages are dropped until it succeeds: {err}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn span_fields(visitor: TextVisitor) -> Vec<(&'static str, String)> {
+    let mut fields = visitor.fields;
+    if let Some(message) = visitor.message {
+        fields.push(("message", message));
+    }
+    fields
+}
+
+// journald field names are uppercase letters, digits and underscores,
+// and they can't start with a digit or an underscore (those are the trusted fields)
+fn field_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("F{name}")
+    }
+}
+
+// `NAME=value\n`, or `NAME\n`, the little-endian 64-bit length, the value and `\n` if the value has newlines
+fn put_field(payload: &mut Vec<u8>, name: &str, value: &[u8]) {
+    payload.extend_from_slice(name.as_bytes());
+    if value.contains(&b'\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+    payload.extend_from_slice(value);
+    payload.push(b'\n');
+}
@@ -0,0 +1,140 @@
+use pyo3::{
+    intern,
+    prelude::*,
+    types::{PyDict, PyString},
+};
+use tracing::{Event, Level, span};
+use tracing_subscriber::{Layer, Registry, layer::Context};
+
+use crate::{
+    imports::get_logging_get_logger,
+    layer::{
+        LayerWithGuard,
+        filter::{PyEnvFilter, ReloadableFilter, reloadable_filter_methods},
+        fmt::to_layer::ToDynLayer,
+        python::{fields::PyFieldVisitor, record::PySpan},
+    },
+    level::PyLevel,
+    logging::bridge,
+};
+
+// forwards the events to logging.getLogger(target), the logger levels and handlers are respected
+#[pyclass(name = "LoggingLayer")]
+pub struct PyLoggingLayer {
+    filter: ReloadableFilter,
+}
+
+#[pymethods]
+impl PyLoggingLayer {
+    #[new]
+    #[pyo3(signature = (*, log_level = PyLevel::Info, filter = None))]
+    fn new(log_level: PyLevel, filter: Option<PyEnvFilter>) -> Self {
+        Self {
+            filter: ReloadableFilter::from_level(log_level, filter),
+        }
+    }
+}
+
+reloadable_filter_methods!(PyLoggingLayer, filter);
+
+impl ToDynLayer for Bound<'_, PyLoggingLayer> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
+        let filter = self.borrow().filter.layer()?;
+        Ok((Box::new(LoggingLayer.with_filter(filter)), None))
+    }
+}
+
+// logging has no TRACE level, 5 is what most libraries that add it use
+fn levelno(level: Level) -> u8 {
+    match level {
+        Level::TRACE => 5,
+        Level::DEBUG => 10,
+        Level::INFO => 20,
+        Level::WARN => 30,
+        Level::ERROR => 40,
+    }
+}
+
+struct LoggingLayer;
+
+impl LoggingLayer {
+    fn forward(py: Python<'_>, event: &Event<'_>, ctx: &Context<'_, Registry>) -> PyResult<()> {
+        let meta = event.metadata();
+        let levelno = levelno(*meta.level());
+
+        let logger = get_logging_get_logger(py).call1((meta.target(),))?;
+        if !logger
+            .call_method1(intern!(py, "isEnabledFor"), (levelno,))?
+            .is_truthy()?
+        {
+            return Ok(());
+        }
+
+        // span fields first, so the inner spans and the event itself override the outer ones
+        let fields = PyDict::new(py);
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(span) = PySpan::from_extensions(py, &span) {
+                    fields.update(span.get().fields.bind(py).as_mapping())?;
+                }
+            }
+        }
+
+        let mut visitor = PyFieldVisitor::new(py);
+        event.record(&mut visitor);
+        let event_fields = visitor.finish()?;
+        let message = match event_fields.get_item("message")? {
+            Some(message) => {
+                event_fields.del_item("message")?;
+                message
+            }
+            None => intern!(py, "").clone().into_any(),
+        };
+        fields.update(event_fields.as_mapping())?;
+
+        let record = logger.call_method1(
+            intern!(py, "makeRecord"),
+            (
+                logger.getattr(intern!(py, "name"))?,
+                levelno,
+                meta.file().unwrap_or("(unknown file)"),
+                meta.line().unwrap_or(0),
+                message,
+                py.None(),
+                py.None(),
+            ),
+        )?;
+
+        // the same as passing the fields as `extra`, except that the fields that clash with the record attributes
+        // are skipped instead of raising an error; all of them are available as record.tracing_fields
+        for (key, value) in &fields {
+            if !record.hasattr(key.cast::<PyString>()?)? {
+                record.setattr(key.cast_into::<PyString>()?, value)?;
+            }
+        }
+        record.setattr(intern!(py, "tracing_fields"), fields)?;
+
+        logger.call_method1(intern!(py, "handle"), (record,))?;
+        Ok(())
+    }
+}
+
+impl Layer<Registry> for LoggingLayer {
+    // span fields are attached to the events, so they're stored in the span extensions
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, Registry>) {
+        Python::try_attach(|py| {
+            if let Err(err) = PySpan::get_or_create(py, attrs, id, &ctx) {
+                err.write_unraisable(py, None);
+            }
+        });
+    }
+
+    // try_attach, as the events may be emitted by rust threads while the interpreter is shutting down
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        Python::try_attach(|py| {
+            if let Some(Err(err)) = bridge(|| Self::forward(py, event, &ctx)) {
+                err.write_unraisable(py, None);
+            }
+        });
+    }
+}
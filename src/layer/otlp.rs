@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use opentelemetry::{KeyValue, trace::TracerProvider as _};
+use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
+use opentelemetry_otlp::{LogExporter, Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource,
+    logs::{self, BatchLogProcessor, SdkLoggerProvider},
+    trace::{self, BatchSpanProcessor, SdkTracerProvider},
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use tokio::runtime::Runtime;
+use tracing::Metadata;
+use tracing_subscriber::{Layer, Registry, filter::filter_fn};
+
+use crate::{
+    layer::{
+        Flush, LayerGuard, LayerWithGuard,
+        filter::{PyEnvFilter, ReloadableFilter, reloadable_filter_methods},
+        fmt::to_layer::ToDynLayer,
+    },
+    level::PyLevel,
+};
+
+// the exporters use tracing themselves, their events must not be exported, or a single event may cause an endless loop
+const EXPORTER_CRATES: &[&str] = &["opentelemetry", "hyper", "h2", "tonic", "tower", "reqwest"];
+
+fn is_exporter_event(meta: &Metadata<'_>) -> bool {
+    let target = meta.target();
+    EXPORTER_CRATES.iter().any(|name| {
+        target
+            .strip_prefix(name)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::") || rest.starts_with('_'))
+    })
+}
+
+#[pyclass(
+    name = "OtlpProtocol",
+    rename_all = "SCREAMING_SNAKE_CASE",
+    from_py_object
+)]
+#[derive(Clone, Copy)]
+pub enum PyOtlpProtocol {
+    HttpProtobuf,
+    Grpc,
+}
+
+impl PyOtlpProtocol {
+    fn default_endpoint(self) -> &'static str {
+        match self {
+            Self::HttpProtobuf => "http://localhost:4318",
+            Self::Grpc => "http://localhost:4317",
+        }
+    }
+}
+
+struct BatchOptions {
+    max_queue_size: usize,
+    max_export_batch_size: usize,
+    scheduled_delay: Duration,
+}
+
+// everything needed to flush or shut down the exporters, the providers are Arcs inside
+#[derive(Clone)]
+struct OtlpProviders {
+    tracer: SdkTracerProvider,
+    logger: Option<SdkLoggerProvider>,
+    // tonic needs a tokio reactor, the batch processors run their own threads otherwise
+    _runtime: Option<Arc<Runtime>>,
+}
+
+impl OtlpProviders {
+    fn force_flush(&self) -> Result<(), String> {
+        self.tracer.force_flush().map_err(|e| e.to_string())?;
+        if let Some(logger) = &self.logger {
+            logger.force_flush().map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+struct OtlpGuard(OtlpProviders);
+
+impl Flush for OtlpGuard {
+    fn flush(&self) -> Result<(), String> {
+        self.0.force_flush()
+    }
+}
+
+impl Drop for OtlpGuard {
+    // shutting down exports the remaining batches, errors can't be reported at this point
+    fn drop(&mut self) {
+        let _ = self.0.tracer.shutdown();
+        if let Some(logger) = &self.0.logger {
+            let _ = logger.shutdown();
+        }
+    }
+}
+
+#[pyclass(name = "OtlpLayer")]
+pub struct PyOtlpLayer {
+    endpoint: String,
+    protocol: PyOtlpProtocol,
+    service_name: Option<String>,
+    resource_attributes: HashMap<String, String>,
+    timeout: Duration,
+    batch: BatchOptions,
+    export_logs: bool,
+    filter: ReloadableFilter,
+    providers: Mutex<Option<OtlpProviders>>,
+}
+
+fn seconds(name: &str, secs: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(secs)
+        .map_err(|e| PyValueError::new_err(format!("invalid {name}: {e}")))
+}
+
+fn exporter_error(err: impl ToString) -> PyErr {
+    PyRuntimeError::new_err(format!(
+        "failed to create an OTLP exporter: {}",
+        err.to_string()
+    ))
+}
+
+#[pymethods]
+impl PyOtlpLayer {
+    #[expect(
+        clippy::too_many_arguments,
+        reason = "same as FmtLayer, it's a python constructor"
+    )]
+    #[new]
+    #[pyo3(signature = (
+        endpoint = None,
+        *,
+        protocol = PyOtlpProtocol::HttpProtobuf,
+        service_name = None,
+        resource_attributes = None,
+        log_level = PyLevel::Info,
+        filter = None,
+        timeout = 10.0,
+        max_queue_size = 2048,
+        max_export_batch_size = 512,
+        scheduled_delay = 5.0,
+        export_logs = true,
+    ))]
+    fn new(
+        endpoint: Option<String>,
+        protocol: PyOtlpProtocol,
+        service_name: Option<String>,
+        resource_attributes: Option<HashMap<String, String>>,
+        log_level: PyLevel,
+        filter: Option<PyEnvFilter>,
+        timeout: f64,
+        max_queue_size: usize,
+        max_export_batch_size: usize,
+        scheduled_delay: f64,
+        export_logs: bool,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            endpoint: endpoint
+                .unwrap_or_else(|| protocol.default_endpoint().to_owned())
+                .trim_end_matches('/')
+                .to_owned(),
+            protocol,
+            service_name,
+            resource_attributes: resource_attributes.unwrap_or_default(),
+            timeout: seconds("timeout", timeout)?,
+            batch: BatchOptions {
+                max_queue_size,
+                max_export_batch_size,
+                scheduled_delay: seconds("scheduled_delay", scheduled_delay)?,
+            },
+            export_logs,
+            filter: ReloadableFilter::from_level(log_level, filter),
+            providers: Mutex::new(None),
+        })
+    }
+
+    // exports everything that's buffered, blocks until it's done or the timeout expires
+    fn flush(&self, py: Python<'_>) -> PyResult<()> {
+        let providers = self.providers.lock().unwrap().clone();
+        let Some(providers) = providers else {
+            return Ok(());
+        };
+        py.detach(|| providers.force_flush())
+            .map_err(PyRuntimeError::new_err)
+    }
+}
+
+reloadable_filter_methods!(PyOtlpLayer, filter);
+
+impl PyOtlpLayer {
+    fn resource(&self) -> Resource {
+        let mut builder = Resource::builder().with_attributes(
+            self.resource_attributes
+                .iter()
+                .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+        );
+        if let Some(service_name) = &self.service_name {
+            builder = builder.with_service_name(service_name.clone());
+        }
+        builder.build()
+    }
+
+    fn span_exporter(&self) -> PyResult<SpanExporter> {
+        match self.protocol {
+            PyOtlpProtocol::HttpProtobuf => SpanExporter::builder()
+                .with_http()
+                .with_protocol(Protocol::HttpBinary)
+                .with_endpoint(format!("{}/v1/traces", self.endpoint))
+                .with_timeout(self.timeout)
+                .build(),
+            PyOtlpProtocol::Grpc => SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(self.endpoint.clone())
+                .with_timeout(self.timeout)
+                .build(),
+        }
+        .map_err(exporter_error)
+    }
+
+    fn log_exporter(&self) -> PyResult<LogExporter> {
+        match self.protocol {
+            PyOtlpProtocol::HttpProtobuf => LogExporter::builder()
+                .with_http()
+                .with_protocol(Protocol::HttpBinary)
+                .with_endpoint(format!("{}/v1/logs", self.endpoint))
+                .with_timeout(self.timeout)
+                .build(),
+            PyOtlpProtocol::Grpc => LogExporter::builder()
+                .with_tonic()
+                .with_endpoint(self.endpoint.clone())
+                .with_timeout(self.timeout)
+                .build(),
+        }
+        .map_err(exporter_error)
+    }
+
+    fn providers(&self) -> PyResult<OtlpProviders> {
+        let runtime = match self.protocol {
+            PyOtlpProtocol::HttpProtobuf => None,
+            PyOtlpProtocol::Grpc => Some(Arc::new(
+                tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(1)
+                    .thread_name("tracing-otlp")
+                    .enable_all()
+                    .build()?,
+            )),
+        };
+        // tonic spawns the connection tasks when the exporter is created
+        let _entered = runtime.as_ref().map(|runtime| runtime.enter());
+
+        let resource = self.resource();
+
+        let span_batch = trace::BatchConfigBuilder::default()
+            .with_max_queue_size(self.batch.max_queue_size)
+            .with_max_export_batch_size(self.batch.max_export_batch_size)
+            .with_scheduled_delay(self.batch.scheduled_delay)
+            .build();
+        let tracer = SdkTracerProvider::builder()
+            .with_span_processor(
+                BatchSpanProcessor::builder(self.span_exporter()?)
+                    .with_batch_config(span_batch)
+                    .build(),
+            )
+            .with_resource(resource.clone())
+            .build();
+
+        let logger = if self.export_logs {
+            let log_batch = logs::BatchConfigBuilder::default()
+                .with_max_queue_size(self.batch.max_queue_size)
+                .with_max_export_batch_size(self.batch.max_export_batch_size)
+                .with_scheduled_delay(self.batch.scheduled_delay)
+                .build();
+            Some(
+                SdkLoggerProvider::builder()
+                    .with_log_processor(
+                        BatchLogProcessor::builder(self.log_exporter()?)
+                            .with_batch_config(log_batch)
+                            .build(),
+                    )
+                    .with_resource(resource)
+                    .build(),
+            )
+        } else {
+            None
+        };
+
+        Ok(OtlpProviders {
+            tracer,
+            logger,
+            _runtime: runtime.clone(),
+        })
+    }
+}
+
+impl ToDynLayer for Bound<'_, PyOtlpLayer> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
+        let this = self.borrow();
+        let providers = this.providers()?;
+
+        // trace and span ids are assigned by the opentelemetry layer, following the span tree;
+        // events are recorded as span events, and also exported as log records, so events outside spans aren't lost
+        let layer = tracing_opentelemetry::layer::<Registry>()
+            .with_tracer(providers.tracer.tracer("tracing-py"))
+            .and_then(
+                providers
+                    .logger
+                    .as_ref()
+                    .map(OpenTelemetryTracingBridge::new),
+            )
+            .with_filter(filter_fn(|meta| !is_exporter_event(meta)))
+            .with_filter(this.filter.layer()?);
+
+        *this.providers.lock().unwrap() = Some(providers.clone());
+        let guard: LayerGuard = Box::new(OtlpGuard(providers));
+        Ok((Box::new(layer), Some(guard)))
+    }
+}
@@ -0,0 +1,200 @@
+pub mod fields;
+pub mod record;
+
+use std::cell::Cell;
+
+use pyo3::{
+    IntoPyObjectExt, PyTypeInfo,
+    prelude::*,
+    types::{PyDict, PyTuple},
+};
+use tracing::{Event, span};
+use tracing_subscriber::{Layer, Registry, layer::Context};
+
+use crate::{
+    layer::{
+        LayerWithGuard,
+        filter::{PyEnvFilter, ReloadableFilter, reloadable_filter_methods},
+        fmt::to_layer::ToDynLayer,
+        python::record::{PyEvent, PySpan},
+    },
+    level::PyLevel,
+};
+
+thread_local! {
+    // events emitted from a python layer callback (e.g. by calling tracing.info() in on_event)
+    // are not passed to python layers, otherwise it'd be an infinite recursion
+    static IN_CALLBACK: Cell<bool> = const { Cell::new(false) };
+}
+
+// resets the flag even if a callback panics
+struct InCallback;
+
+impl Drop for InCallback {
+    fn drop(&mut self) {
+        IN_CALLBACK.set(false);
+    }
+}
+
+// base class for layers implemented in python, subclasses override the methods they need
+#[pyclass(name = "Layer", subclass)]
+pub struct PyLayer {
+    filter: ReloadableFilter,
+}
+
+#[pymethods]
+impl PyLayer {
+    // accepts anything else, so subclasses can have their own __init__ signature
+    #[new]
+    #[pyo3(signature = (*_args, log_level = PyLevel::Info, filter = None, **_kwargs))]
+    fn new(
+        _args: &Bound<'_, PyTuple>,
+        log_level: PyLevel,
+        filter: Option<PyEnvFilter>,
+        _kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> Self {
+        Self {
+            filter: ReloadableFilter::from_level(log_level, filter),
+        }
+    }
+
+    fn on_event(&self, _event: &Bound<'_, PyEvent>) {}
+
+    fn on_new_span(&self, _span: &Bound<'_, PySpan>) {}
+
+    fn on_enter(&self, _span: &Bound<'_, PySpan>) {}
+
+    fn on_exit(&self, _span: &Bound<'_, PySpan>) {}
+
+    fn on_close(&self, _span: &Bound<'_, PySpan>) {}
+}
+
+reloadable_filter_methods!(PyLayer, filter);
+
+struct Overridden {
+    on_event: bool,
+    on_new_span: bool,
+    on_enter: bool,
+    on_exit: bool,
+    on_close: bool,
+}
+
+impl Overridden {
+    // calling a method that does nothing still requires the GIL, so methods that aren't overridden are skipped
+    fn new(layer: &Bound<'_, PyLayer>) -> PyResult<Self> {
+        let py = layer.py();
+        let base = PyLayer::type_object(py);
+        let class = layer.get_type();
+
+        let is_overridden =
+            |name: &str| -> PyResult<bool> { Ok(!class.getattr(name)?.is(base.getattr(name)?)) };
+
+        Ok(Self {
+            on_event: is_overridden("on_event")?,
+            on_new_span: is_overridden("on_new_span")?,
+            on_enter: is_overridden("on_enter")?,
+            on_exit: is_overridden("on_exit")?,
+            on_close: is_overridden("on_close")?,
+        })
+    }
+
+    fn any_span_method(&self) -> bool {
+        self.on_event || self.on_new_span || self.on_enter || self.on_exit || self.on_close
+    }
+}
+
+pub struct PythonLayer {
+    layer: Py<PyLayer>,
+    overridden: Overridden,
+}
+
+impl PythonLayer {
+    fn call<'py>(&self, py: Python<'py>, method: &str, arg: impl IntoPyObjectExt<'py>) {
+        let result = arg
+            .into_bound_py_any(py)
+            .and_then(|arg| self.layer.bind(py).call_method1(method, (arg,)));
+
+        if let Err(err) = result {
+            err.write_unraisable(py, Some(self.layer.bind(py).as_any()));
+        }
+    }
+
+    // the GIL is reacquired here, as the events may be emitted from threads that don't hold it
+    fn with_gil(&self, f: impl FnOnce(Python<'_>)) {
+        if IN_CALLBACK.replace(true) {
+            return;
+        }
+        let _in_callback = InCallback;
+
+        Python::attach(f);
+    }
+
+    fn span_method(&self, method: &str, id: &span::Id, ctx: &Context<'_, Registry>) {
+        self.with_gil(|py| {
+            let span = ctx
+                .span(id)
+                .and_then(|span| PySpan::from_extensions(py, &span));
+            if let Some(span) = span {
+                self.call(py, method, span);
+            }
+        });
+    }
+}
+
+impl Layer<Registry> for PythonLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, Registry>) {
+        // the span object is needed for every callback, as events reference their spans
+        if !self.overridden.any_span_method() {
+            return;
+        }
+
+        self.with_gil(|py| match PySpan::get_or_create(py, attrs, id, &ctx) {
+            Ok(span) => {
+                if self.overridden.on_new_span {
+                    self.call(py, "on_new_span", span);
+                }
+            }
+            Err(err) => err.write_unraisable(py, Some(self.layer.bind(py).as_any())),
+        });
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, Registry>) {
+        if !self.overridden.on_event {
+            return;
+        }
+
+        self.with_gil(|py| match PyEvent::new(py, event, &ctx) {
+            Ok(event) => self.call(py, "on_event", event),
+            Err(err) => err.write_unraisable(py, Some(self.layer.bind(py).as_any())),
+        });
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, Registry>) {
+        if self.overridden.on_enter {
+            self.span_method("on_enter", id, &ctx);
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, Registry>) {
+        if self.overridden.on_exit {
+            self.span_method("on_exit", id, &ctx);
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, Registry>) {
+        if self.overridden.on_close {
+            self.span_method("on_close", &id, &ctx);
+        }
+    }
+}
+
+impl ToDynLayer for Bound<'_, PyLayer> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
+        let layer = PythonLayer {
+            layer: self.clone().unbind(),
+            overridden: Overridden::new(self)?,
+        };
+        let filter = self.borrow().filter.layer()?;
+        Ok((Box::new(layer.with_filter(filter)), None))
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::Debug;
+
+use pyo3::{
+    IntoPyObjectExt,
+    prelude::*,
+    types::{PyDict, PyString},
+};
+use tracing::field::{Field, Visit};
+use valuable::Value;
+
+// collects the recorded fields into a dict, values that have no python equivalent are converted to strings
+//
+// objects passed from python are already converted to OwnedValuable at this point,
+// so anything that isn't a primitive is received as its repr()
+pub struct PyFieldVisitor<'py> {
+    dict: Bound<'py, PyDict>,
+    error: Option<PyErr>,
+}
+
+impl<'py> PyFieldVisitor<'py> {
+    pub fn new(py: Python<'py>) -> Self {
+        Self {
+            dict: PyDict::new(py),
+            error: None,
+        }
+    }
+
+    pub fn finish(self) -> PyResult<Bound<'py, PyDict>> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.dict),
+        }
+    }
+
+    fn set(&mut self, field: &Field, value: PyResult<Bound<'py, PyAny>>) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(err) = value.and_then(|value| self.dict.set_item(field.name(), value)) {
+            self.error = Some(err);
+        }
+    }
+
+    fn set_value<T: IntoPyObjectExt<'py>>(&mut self, field: &Field, value: T) {
+        let py = self.dict.py();
+        self.set(field, value.into_bound_py_any(py));
+    }
+}
+
+impl Visit for PyFieldVisitor<'_> {
+    fn record_value(&mut self, field: &Field, value: Value<'_>) {
+        let py = self.dict.py();
+        self.set(field, valuable_to_py(py, value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set_value(field, value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set_value(field, value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set_value(field, value);
+    }
+
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.set_value(field, value);
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.set_value(field, value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set_value(field, value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set_value(field, value);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.set_value(field, format!("{value:?}"));
+    }
+}
+
+pub fn valuable_to_py<'py>(py: Python<'py>, value: Value<'_>) -> PyResult<Bound<'py, PyAny>> {
+    match value {
+        Value::Bool(bool) => bool.into_bound_py_any(py),
+        Value::I64(int) => int.into_bound_py_any(py),
+        Value::U64(int) => int.into_bound_py_any(py),
+        Value::I128(int) => int.into_bound_py_any(py),
+        Value::U128(int) => int.into_bound_py_any(py),
+        Value::F64(float) => float.into_bound_py_any(py),
+        Value::String(str) | Value::UnquotedString(str) => Ok(PyString::new(py, str).into_any()),
+        Value::Unit => Ok(py.None().into_bound(py)),
+        other => format!("{other:?}").into_bound_py_any(py),
+    }
+}
@@ -0,0 +1,137 @@
+use pyo3::{prelude::*, types::PyDict};
+use tracing::{Event, Level, Metadata, Subscriber, span};
+use tracing_subscriber::{
+    layer::Context,
+    registry::{LookupSpan, SpanRef},
+};
+
+use crate::{layer::python::fields::PyFieldVisitor, level::PyLevel};
+
+#[pyclass(name = "Event", frozen, get_all)]
+pub struct PyEvent {
+    level: PyLevel,
+    name: &'static str,
+    target: &'static str,
+    module: Option<&'static str>,
+    file: Option<&'static str>,
+    line: Option<u32>,
+    fields: Py<PyDict>,
+    // the span the event was emitted in, if there is one
+    span: Option<Py<PySpan>>,
+}
+
+impl PyEvent {
+    pub fn new<S>(py: Python<'_>, event: &Event<'_>, ctx: &Context<'_, S>) -> PyResult<Self>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let meta = event.metadata();
+
+        let mut visitor = PyFieldVisitor::new(py);
+        event.record(&mut visitor);
+
+        Ok(Self {
+            level: PyLevel::from(*meta.level()),
+            name: meta.name(),
+            target: meta.target(),
+            module: meta.module_path(),
+            file: meta.file(),
+            line: meta.line(),
+            fields: visitor.finish()?.unbind(),
+            span: ctx
+                .event_span(event)
+                .and_then(|span| PySpan::from_extensions(py, &span)),
+        })
+    }
+}
+
+#[pymethods]
+impl PyEvent {
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "Event(level={:?}, target={:?}, fields={})",
+            Level::from(self.level),
+            self.target,
+            self.fields.bind(py)
+        )
+    }
+}
+
+// the same object is passed to on_new_span/on_enter/on_exit/on_close, so it may be used as a dict key
+#[pyclass(name = "Span", frozen, get_all)]
+pub struct PySpan {
+    pub id: u64,
+    pub level: PyLevel,
+    pub name: &'static str,
+    pub target: &'static str,
+    pub module: Option<&'static str>,
+    pub file: Option<&'static str>,
+    pub line: Option<u32>,
+    pub fields: Py<PyDict>,
+    pub parent: Option<Py<PySpan>>,
+}
+
+impl PySpan {
+    // the span object is stored in the span extensions, so every python layer gets the same object
+    pub fn get_or_create<S>(
+        py: Python<'_>,
+        attrs: &span::Attributes<'_>,
+        id: &span::Id,
+        ctx: &Context<'_, S>,
+    ) -> PyResult<Py<Self>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let span = ctx.span(id).expect("span must exist in on_new_span");
+
+        if let Some(existing) = Self::from_extensions(py, &span) {
+            return Ok(existing);
+        }
+
+        let mut visitor = PyFieldVisitor::new(py);
+        attrs.record(&mut visitor);
+
+        let meta: &Metadata<'_> = attrs.metadata();
+        let new = Py::new(
+            py,
+            Self {
+                id: id.into_u64(),
+                level: PyLevel::from(*meta.level()),
+                name: meta.name(),
+                target: meta.target(),
+                module: meta.module_path(),
+                file: meta.file(),
+                line: meta.line(),
+                fields: visitor.finish()?.unbind(),
+                parent: span
+                    .parent()
+                    .and_then(|parent| Self::from_extensions(py, &parent)),
+            },
+        )?;
+
+        span.extensions_mut().insert(new.clone_ref(py));
+        Ok(new)
+    }
+
+    pub fn from_extensions<'a, R: LookupSpan<'a>>(
+        py: Python<'_>,
+        span: &SpanRef<'a, R>,
+    ) -> Option<Py<Self>> {
+        span.extensions()
+            .get::<Py<Self>>()
+            .map(|span| span.clone_ref(py))
+    }
+}
+
+#[pymethods]
+impl PySpan {
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "Span(id={}, name={:?}, target={:?}, fields={})",
+            self.id,
+            self.name,
+            self.target,
+            self.fields.bind(py)
+        )
+    }
+}
@@ -0,0 +1,531 @@
+use std::{
+    io::{self, Write},
+    net::{Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use time::{
+    OffsetDateTime, UtcOffset, format_description::BorrowedFormatItem, macros::format_description,
+};
+use tracing::{Event, Level};
+use tracing_subscriber::{Layer, Registry, layer::Context};
+
+use crate::{
+    imports::get_socket_gethostname,
+    layer::{
+        LayerWithGuard,
+        fields::TextVisitor,
+        filter::{PyEnvFilter, ReloadableFilter, reloadable_filter_methods},
+        fmt::to_layer::ToDynLayer,
+        fork::ForkSafeMutex,
+    },
+    level::PyLevel,
+};
+
+// rfc 5424 allows at most 6 digits of the fractional second
+const RFC5424_TIMESTAMP: &[BorrowedFormatItem<'_>] = format_description!(
+    "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:6][offset_hour sign:mandatory]:[offset_minute]"
+);
+const RFC3164_TIMESTAMP: &[BorrowedFormatItem<'_>] =
+    format_description!("[month repr:short] [day padding:space] [hour]:[minute]:[second]");
+
+const NILVALUE: &str = "-";
+
+// the tcp connection is made on the logging thread, these bound how long an event may wait for it
+const TCP_TIMEOUT: Duration = Duration::from_secs(1);
+const TCP_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const TCP_MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[pyclass(name = "SyslogFacility", rename_all = "UPPERCASE", from_py_object)]
+#[derive(Clone, Copy)]
+pub enum PySyslogFacility {
+    Kern = 0,
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    Authpriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+#[pyclass(name = "SyslogFormat", rename_all = "UPPERCASE", from_py_object)]
+#[derive(Clone, Copy)]
+pub enum PySyslogFormat {
+    Rfc5424,
+    Rfc3164,
+}
+
+pub fn severity(level: Level) -> u8 {
+    match level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    }
+}
+
+#[derive(Clone)]
+enum SyslogAddress {
+    #[cfg(unix)]
+    Unix(PathBuf),
+    Udp(String),
+    Tcp(String),
+}
+
+impl SyslogAddress {
+    fn parse(address: &str) -> PyResult<Self> {
+        // `[::1]` and a bare `::1` are ipv6 addresses without a port
+        let with_port = |host: &str| {
+            if host.parse::<Ipv6Addr>().is_ok() {
+                format!("[{host}]:514")
+            } else if host.starts_with('[') {
+                if host.contains("]:") {
+                    host.to_owned()
+                } else {
+                    format!("{host}:514")
+                }
+            } else if host.contains(':') {
+                host.to_owned()
+            } else {
+                format!("{host}:514")
+            }
+        };
+        if let Some(host) = address.strip_prefix("udp://") {
+            Ok(Self::Udp(with_port(host)))
+        } else if let Some(host) = address.strip_prefix("tcp://") {
+            Ok(Self::Tcp(with_port(host)))
+        } else {
+            Self::unix(address.strip_prefix("unix://").unwrap_or(address))
+        }
+    }
+
+    #[cfg(unix)]
+    #[expect(clippy::unnecessary_wraps, reason = "it's an error on other platforms")]
+    fn unix(path: &str) -> PyResult<Self> {
+        Ok(Self::Unix(PathBuf::from(path)))
+    }
+
+    #[cfg(not(unix))]
+    fn unix(path: &str) -> PyResult<Self> {
+        Err(PyValueError::new_err(format!(
+            "unix sockets are not supported on this platform, expected udp://host:port or tcp://host:port, got {path}"
+        )))
+    }
+}
+
+enum Connection {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+    Tcp(TcpConnection),
+}
+
+impl Connection {
+    fn open(address: &SyslogAddress) -> io::Result<Self> {
+        Ok(match address {
+            #[cfg(unix)]
+            SyslogAddress::Unix(path) => Self::Unix(connect_unix(path)?),
+            SyslogAddress::Udp(host) => {
+                let socket = UdpSocket::bind(if host.starts_with('[') {
+                    "[::]:0"
+                } else {
+                    "0.0.0.0:0"
+                })?;
+                socket.connect(host)?;
+                Self::Udp(socket)
+            }
+            SyslogAddress::Tcp(host) => Self::Tcp(TcpConnection::open(host)?),
+        })
+    }
+
+    fn send(&mut self, address: &SyslogAddress, message: &[u8]) -> io::Result<()> {
+        match (self, address) {
+            #[cfg(unix)]
+            (Self::Unix(socket), SyslogAddress::Unix(path)) => {
+                // the socket is recreated when the syslog daemon restarts
+                if socket.send(message).is_err() {
+                    *socket = connect_unix(path)?;
+                    socket.send(message)?;
+                }
+                Ok(())
+            }
+            (Self::Udp(socket), _) => socket.send(message).map(|_| ()),
+            (Self::Tcp(connection), _) => connection.send(message),
+            #[cfg(unix)]
+            _ => unreachable!("the connection is opened from the same address"),
+        }
+    }
+}
+
+// the host is resolved once, so dns isn't queried on the logging thread; if the connection is lost,
+// it's reestablished with a backoff and the messages are dropped until then, so a missing receiver
+// doesn't make every event wait for the timeout
+struct TcpConnection {
+    addrs: Vec<SocketAddr>,
+    stream: Option<TcpStream>,
+    next_attempt: Instant,
+    delay: Duration,
+}
+
+impl TcpConnection {
+    fn open(host: &str) -> io::Result<Self> {
+        let mut connection = Self {
+            addrs: host.to_socket_addrs()?.collect(),
+            stream: None,
+            next_attempt: Instant::now(),
+            delay: TCP_RECONNECT_DELAY,
+        };
+        connection.stream = Some(connection.connect()?);
+        Ok(connection)
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, "the host didn't resolve");
+        for addr in &self.addrs {
+            match TcpStream::connect_timeout(addr, TCP_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_write_timeout(Some(TCP_TIMEOUT))?;
+                    return Ok(stream);
+                }
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        // rfc 6587 octet counting, the messages may contain newlines
+        let mut framed = format!("{} ", message.len()).into_bytes();
+        framed.extend_from_slice(message);
+
+        if let Some(stream) = &mut self.stream {
+            if stream.write_all(&framed).is_ok() {
+                return Ok(());
+            }
+            // the connection was probably closed by the receiver, reconnecting right away
+            self.stream = None;
+            self.next_attempt = Instant::now();
+        }
+
+        let now = Instant::now();
+        if now < self.next_attempt {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "waiting to reconnect",
+            ));
+        }
+        let result = self
+            .connect()
+            .and_then(|mut stream| stream.write_all(&framed).map(|()| stream));
+        match result {
+            Ok(stream) => {
+                self.stream = Some(stream);
+                self.delay = TCP_RECONNECT_DELAY;
+                Ok(())
+            }
+            Err(err) => {
+                self.next_attempt = now + self.delay;
+                self.delay = (self.delay * 2).min(TCP_MAX_RECONNECT_DELAY);
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn connect_unix(path: &Path) -> io::Result<UnixDatagram> {
+    let socket = UnixDatagram::unbound()?;
+    socket.connect(path)?;
+    Ok(socket)
+}
+
+#[pyclass(name = "SyslogLayer")]
+pub struct PySyslogLayer {
+    address: SyslogAddress,
+    config: SyslogConfig,
+    filter: ReloadableFilter,
+    dropped_messages: Arc<AtomicU64>,
+}
+
+#[derive(Clone)]
+struct SyslogConfig {
+    format: PySyslogFormat,
+    facility: PySyslogFacility,
+    hostname: String,
+    app_name: String,
+    // the current pid if it's not set
+    procid: Option<String>,
+    structured_data_id: String,
+    // the local time is used for rfc 3164 timestamps, computing the offset may fail later if there are multiple threads
+    local_offset: UtcOffset,
+    // local syslog daemons don't expect a hostname in rfc 3164 messages
+    with_hostname: bool,
+}
+
+#[pymethods]
+impl PySyslogLayer {
+    #[expect(
+        clippy::too_many_arguments,
+        reason = "same as FmtLayer, it's a python constructor"
+    )]
+    #[new]
+    #[pyo3(signature = (
+        address = "/dev/log",
+        *,
+        format = PySyslogFormat::Rfc5424,
+        facility = PySyslogFacility::User,
+        app_name = None,
+        procid = None,
+        hostname = None,
+        structured_data_id = "tracing@32473",
+        log_level = PyLevel::Info,
+        filter = None,
+    ))]
+    fn new(
+        py: Python<'_>,
+        address: &str,
+        format: PySyslogFormat,
+        facility: PySyslogFacility,
+        app_name: Option<String>,
+        procid: Option<String>,
+        hostname: Option<String>,
+        structured_data_id: &str,
+        log_level: PyLevel,
+        filter: Option<PyEnvFilter>,
+    ) -> PyResult<Self> {
+        let address = SyslogAddress::parse(address)?;
+        let app_name = match app_name {
+            Some(app_name) => app_name,
+            None => default_app_name(py)?,
+        };
+        let hostname = match hostname {
+            Some(hostname) => hostname,
+            None => get_socket_gethostname(py).call0()?.extract()?,
+        };
+        if structured_data_id.is_empty() {
+            return Err(PyValueError::new_err(
+                "structured_data_id must not be empty",
+            ));
+        }
+
+        #[cfg(unix)]
+        let with_hostname = !matches!(address, SyslogAddress::Unix(_));
+        #[cfg(not(unix))]
+        let with_hostname = true;
+
+        Ok(Self {
+            address,
+            config: SyslogConfig {
+                format,
+                facility,
+                hostname: header_field(&hostname, 255),
+                app_name: header_field(&app_name, 48),
+                procid: procid.map(|procid| header_field(&procid, 128)),
+                structured_data_id: sd_name(structured_data_id),
+                local_offset: UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC),
+                with_hostname,
+            },
+            filter: ReloadableFilter::from_level(log_level, filter),
+            dropped_messages: Arc::default(),
+        })
+    }
+
+    #[getter]
+    fn dropped_messages(&self) -> u64 {
+        self.dropped_messages.load(Ordering::Relaxed)
+    }
+}
+
+reloadable_filter_methods!(PySyslogLayer, filter);
+
+// the script name, like python's logging.handlers.SysLogHandler ident
+pub fn default_app_name(py: Python<'_>) -> PyResult<String> {
+    let argv = py.import("sys")?.getattr("argv")?;
+    let script = argv
+        .get_item(0)
+        .ok()
+        .and_then(|x| x.extract::<String>().ok())
+        .unwrap_or_default();
+    Ok(Path::new(&script)
+        .file_name()
+        .and_then(|x| x.to_str())
+        .filter(|x| !x.is_empty())
+        .unwrap_or("python")
+        .to_owned())
+}
+
+impl ToDynLayer for Bound<'_, PySyslogLayer> {
+    fn dyn_layer(&self) -> PyResult<LayerWithGuard> {
+        let this = self.borrow();
+        let layer = SyslogLayer {
+            connection: ForkSafeMutex::new(Connection::open(&this.address)?),
+            address: this.address.clone(),
+            config: this.config.clone(),
+            dropped_messages: this.dropped_messages.clone(),
+            failing: AtomicBool::new(false),
+        };
+        Ok((Box::new(layer.with_filter(this.filter.layer()?)), None))
+    }
+}
+
+struct SyslogLayer {
+    connection: ForkSafeMutex<Connection>,
+    address: SyslogAddress,
+    config: SyslogConfig,
+    dropped_messages: Arc<AtomicU64>,
+    // only the first error is printed, until a message is sent again
+    failing: AtomicBool,
+}
+
+impl SyslogLayer {
+    fn format(&self, event: &Event<'_>) -> String {
+        let config = &self.config;
+        let meta = event.metadata();
+
+        let mut visitor = TextVisitor::default();
+        event.record(&mut visitor);
+
+        let pri = config.facility as u8 * 8 + severity(*meta.level());
+        let procid = config
+            .procid
+            .clone()
+            .unwrap_or_else(|| process::id().to_string());
+        let message = visitor.message.unwrap_or_default();
+
+        match config.format {
+            PySyslogFormat::Rfc5424 => {
+                let timestamp = OffsetDateTime::now_utc()
+                    .format(RFC5424_TIMESTAMP)
+                    .unwrap_or_else(|_| NILVALUE.to_owned());
+
+                let mut data = format!(
+                    "[{} target=\"{}\"",
+                    config.structured_data_id,
+                    sd_value(meta.target())
+                );
+                for (name, value) in &visitor.fields {
+                    data.push_str(&format!(" {}=\"{}\"", sd_name(name), sd_value(value)));
+                }
+                data.push(']');
+
+                let mut line = format!(
+                    "<{pri}>1 {timestamp} {} {} {procid} {NILVALUE} {data}",
+                    config.hostname, config.app_name
+                );
+                if !message.is_empty() {
+                    line.push(' ');
+                    line.push_str(&message);
+                }
+                line
+            }
+            PySyslogFormat::Rfc3164 => {
+                let timestamp = OffsetDateTime::now_utc()
+                    .to_offset(config.local_offset)
+                    .format(RFC3164_TIMESTAMP)
+                    .unwrap_or_default();
+                let hostname = if config.with_hostname {
+                    format!("{} ", config.hostname)
+                } else {
+                    String::new()
+                };
+
+                let mut line = format!(
+                    "<{pri}>{timestamp} {hostname}{}[{procid}]: {message}",
+                    config.app_name
+                );
+                for (name, value) in &visitor.fields {
+                    line.push_str(&format!(" {name}={}", quote_if_needed(value)));
+                }
+                line
+            }
+        }
+    }
+}
+
+impl Layer<Registry> for SyslogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, Registry>) {
+        let line = self.format(event);
+        let result = self.connection.lock().send(&self.address, line.as_bytes());
+        match result {
+            Ok(()) => self.failing.store(false, Ordering::Relaxed),
+            Err(err) => {
+                self.dropped_messages.fetch_add(1, Ordering::Relaxed);
+                if !self.failing.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "tracing: failed to send a syslog message, the messages are dropped until it succeeds: {err}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+// printable ascii without spaces, "-" if it's empty
+fn header_field(value: &str, max_len: usize) -> String {
+    let value = value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max_len)
+        .collect::<String>();
+    if value.is_empty() {
+        NILVALUE.to_owned()
+    } else {
+        value
+    }
+}
+
+// sd-name is printable ascii except '=', ' ', ']' and '"', at most 32 characters
+fn sd_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(32)
+        .collect()
+}
+
+fn sd_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn quote_if_needed(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+        format!("{value:?}")
+    } else {
+        value.to_owned()
+    }
+}
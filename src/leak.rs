@@ -1,6 +1,9 @@
 pub mod debug;
 
-use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::{
+    any::Any,
+    sync::{LazyLock, Mutex, MutexGuard},
+};
 
 use rapidhash::RapidHashSet;
 use tracing::warn;
@@ -10,6 +13,14 @@ static LEAKED_STRINGS: LazyLock<Mutex<RapidHashSet<&'static str>>> = LazyLock::n
 static LEAKED_SLICES: LazyLock<Mutex<RapidHashSet<&'static [&'static str]>>> =
     LazyLock::new(Mutex::default);
 
+// same as callsite::try_lock_for_fork, both sets or none
+pub fn try_lock_for_fork() -> Option<impl Any> {
+    Some((
+        LEAKED_STRINGS.try_lock().ok()?,
+        LEAKED_SLICES.try_lock().ok()?,
+    ))
+}
+
 pub fn leak<T>(x: T) -> &'static T {
     Box::leak(Box::new(x))
 }
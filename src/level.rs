@@ -1,8 +1,15 @@
 use pyo3::prelude::*;
 use tracing::Level;
 
-#[pyclass(name = "Level", rename_all = "UPPERCASE", from_py_object)]
-#[derive(Clone, Copy)]
+#[pyclass(
+    name = "Level",
+    rename_all = "UPPERCASE",
+    from_py_object,
+    eq,
+    hash,
+    frozen
+)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PyLevel {
     Trace,
     Debug,
@@ -33,3 +40,15 @@ impl From<PyLevel> for tracing::Level {
         }
     }
 }
+
+impl From<Level> for PyLevel {
+    fn from(value: Level) -> Self {
+        match value {
+            Level::TRACE => Self::Trace,
+            Level::DEBUG => Self::Debug,
+            Level::INFO => Self::Info,
+            Level::WARN => Self::Warn,
+            Level::ERROR => Self::Error,
+        }
+    }
+}
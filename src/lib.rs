@@ -1,6 +1,7 @@
 #![feature(exact_size_is_empty)]
 #![feature(trait_alias)]
 #![feature(decl_macro)]
+#![cfg_attr(target_os = "linux", feature(unix_socket_ancillary_data))]
 #![warn(clippy::allow_attributes)]
 
 mod cached;
@@ -14,6 +15,7 @@ mod introspect;
 mod layer;
 mod leak;
 mod level;
+mod logging;
 mod py_type;
 mod span;
 mod template;
@@ -27,6 +29,7 @@ static GLOBAL: MiMalloc = MiMalloc;
 
 #[pymodule(name = "tracing")]
 mod tracing {
+    use pyo3::prelude::*;
 
     #[pymodule_export]
     use super::level::PyLevel;
@@ -36,21 +39,52 @@ mod tracing {
 
     #[pymodule_export]
     use super::layer::{
+        capture::{PyCaptureLayer, PyCapturedRecord},
+        chrome::PyChromeTraceLayer,
+        filter::{PyEnvFilter, py_set_filter, py_set_level},
+        flame::{PyFlameLayer, PyFlameMode},
         fmt::{
             FmtLayer, PyFormat,
+            ecs::EcsFormat,
             file::{NonBlocking, PyLogFile, PyRollingLog},
-            rotation::PyRotation,
+            json::{JsonFormat, PyEpoch},
+            reopen::py_reopen_logs,
+            rotation::{PyCompression, PyRotation},
+            socket::PySocketLog,
             span::PyFmtSpan,
             time::format::PyTimeFormat,
             time::timer::PyTimer,
             time::timer::Time,
         },
-        py_init,
+        logging::PyLoggingLayer,
+        py_flush, py_init, py_shutdown,
+        python::{
+            PyLayer,
+            record::{PyEvent, PySpan},
+        },
+        syslog::{PySyslogFacility, PySyslogFormat, PySyslogLayer},
     };
 
+    #[cfg(unix)]
+    #[pymodule_export]
+    use super::layer::fmt::reopen::py_reopen_logs_on_sighup;
+
+    #[cfg(target_os = "linux")]
+    #[pymodule_export]
+    use super::layer::journald::PyJournaldLayer;
+
+    #[cfg(feature = "otlp")]
+    #[pymodule_export]
+    use super::layer::otlp::{PyOtlpLayer, PyOtlpProtocol};
+
     #[pymodule_export]
     use super::leak::debug::leak_info;
 
     #[pymodule_export]
     use super::instrument::py_instrument;
+
+    #[pymodule_init]
+    fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+        m.add("LoggingHandler", super::logging::handler_class(m.py())?)
+    }
 }
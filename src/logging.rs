@@ -0,0 +1,142 @@
+use std::cell::Cell;
+
+use pyo3::{
+    intern,
+    prelude::*,
+    types::{PyDict, PyString, PyTuple, PyType},
+};
+use tracing::{Event, Level, Value};
+use tracing_core::Callsite;
+
+use crate::{
+    callsite::{self, RecordLocation},
+    imports::{get_logging_handler_type, get_traceback_format_exception},
+};
+
+thread_local! {
+    // set while a message is passed from logging to tracing or the other way around,
+    // so LoggingHandler and LoggingLayer installed together don't pass the same message back and forth
+    static BRIDGING: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn bridge<R>(f: impl FnOnce() -> R) -> Option<R> {
+    if BRIDGING.replace(true) {
+        return None;
+    }
+    let _bridging = Bridging;
+
+    Some(f())
+}
+
+// resets the flag even if f() panics
+struct Bridging;
+
+impl Drop for Bridging {
+    fn drop(&mut self) {
+        BRIDGING.set(false);
+    }
+}
+
+static MESSAGE: &[&str] = &["message"];
+static MESSAGE_AND_EXCEPTION: &[&str] = &["message", "exception"];
+
+// pyo3 classes can't extend python classes, so a logging.Handler subclass is created at runtime;
+// emit is a builtin function, which isn't bound to the handler, so it's called with the record only
+pub fn handler_class(py: Python<'_>) -> PyResult<Bound<'_, PyType>> {
+    let namespace = PyDict::new(py);
+    namespace.set_item("__module__", "tracing")?;
+    namespace.set_item(
+        "__doc__",
+        "logging.Handler that emits the records as tracing events",
+    )?;
+    namespace.set_item("emit", wrap_pyfunction!(emit, py)?)?;
+
+    py.get_type::<PyType>()
+        .call1(("LoggingHandler", (get_logging_handler_type(py),), namespace))?
+        .cast_into::<PyType>()
+        .map_err(Into::into)
+}
+
+// logging has no TRACE, anything below DEBUG is mapped to it
+fn level(levelno: i64) -> Level {
+    match levelno {
+        40.. => Level::ERROR,
+        30..40 => Level::WARN,
+        20..30 => Level::INFO,
+        10..20 => Level::DEBUG,
+        _ => Level::TRACE,
+    }
+}
+
+// logging.Handler.handle() doesn't catch the exceptions raised by emit(), so they're reported here
+#[pyfunction]
+fn emit(record: &Bound<'_, PyAny>) {
+    if let Some(Err(err)) = bridge(|| emit_record(record)) {
+        err.write_unraisable(record.py(), Some(record));
+    }
+}
+
+fn emit_record(record: &Bound<'_, PyAny>) -> PyResult<()> {
+    let py = record.py();
+
+    let level = level(record.getattr(intern!(py, "levelno"))?.extract()?);
+    if !callsite::is_level_enabled(level) {
+        return Ok(());
+    }
+
+    let exc_info = record
+        .getattr(intern!(py, "exc_info"))?
+        .cast_into::<PyTuple>()
+        .ok();
+    let fields = if exc_info.is_some() {
+        MESSAGE_AND_EXCEPTION
+    } else {
+        MESSAGE
+    };
+
+    let location = RecordLocation {
+        target: record.getattr(intern!(py, "name"))?.extract()?,
+        file: record.getattr(intern!(py, "pathname"))?.extract()?,
+        line: record.getattr(intern!(py, "lineno"))?.extract()?,
+    };
+    let callsite = callsite::get_or_init_record_callsite(location, level, fields);
+    if !callsite::is_enabled(callsite) {
+        return Ok(());
+    }
+
+    // formatting is done only if the event is enabled, like the logging module does
+    let message = record
+        .call_method0(intern!(py, "getMessage"))?
+        .cast_into::<PyString>()?;
+    let message = message.to_cow()?;
+
+    let exception = match exc_info {
+        Some(exc_info) => Some(format_exception(record, &exc_info)?),
+        None => None,
+    };
+
+    let meta = callsite.metadata();
+    let values: &[Option<&dyn Value>] = match &exception {
+        Some(exception) => &[Some(&&*message), Some(&exception.as_str())],
+        None => &[Some(&&*message)],
+    };
+    Event::dispatch(meta, &meta.fields().value_set_all(values));
+    Ok(())
+}
+
+// reusing the traceback cached by logging.Formatter, if another handler has already formatted it
+fn format_exception(record: &Bound<'_, PyAny>, exc_info: &Bound<'_, PyTuple>) -> PyResult<String> {
+    let py = record.py();
+
+    let exc_text = record.getattr(intern!(py, "exc_text"))?;
+    if !exc_text.is_none() {
+        return exc_text.extract();
+    }
+
+    let lines = get_traceback_format_exception(py).call1(exc_info)?;
+    Ok(PyString::new(py, "")
+        .call_method1(intern!(py, "join"), (lines,))?
+        .extract::<String>()?
+        .trim_end()
+        .to_owned())
+}
@@ -0,0 +1,12 @@
+from typing import Iterator
+
+import pytest
+
+import tracing
+
+
+@pytest.fixture(autouse=True)
+def shutdown_tracing() -> Iterator[None]:
+    """The subscriber is global, every test starts without layers"""
+    yield
+    tracing.shutdown()
@@ -0,0 +1,83 @@
+import logging
+import threading
+
+import pytest
+
+import tracing
+from tracing import CaptureLayer, Level
+
+
+def test_fixture_captures_events(tracing_capture: CaptureLayer) -> None:
+    tracing.warn("login failed", user="admin")
+
+    record = tracing_capture.assert_logged(Level.WARN, "login failed", user="admin")
+    assert record.kind == "event"
+    assert record.fields == {"user": "admin"}
+    tracing_capture.assert_not_logged(Level.ERROR)
+
+
+def test_fixture_is_scoped_to_the_thread(tracing_capture: CaptureLayer) -> None:
+    thread = threading.Thread(target=lambda: tracing.info("other thread"))
+    thread.start()
+    thread.join()
+
+    tracing_capture.assert_not_logged(message="other thread")
+
+
+def test_nested_inside_fixture(tracing_capture: CaptureLayer) -> None:
+    with CaptureLayer() as inner:
+        tracing.info("inner")
+    tracing.info("outer")
+
+    inner.assert_logged(message="inner")
+    inner.assert_not_logged(message="outer")
+    tracing_capture.assert_logged(message="outer")
+    tracing_capture.assert_not_logged(message="inner")
+
+
+def test_exit_out_of_order() -> None:
+    outer, inner = CaptureLayer(), CaptureLayer()
+    outer.__enter__()
+    inner.__enter__()
+
+    with pytest.raises(RuntimeError):
+        outer.__exit__(None, None, None)
+
+    # nothing was popped, the inner layer still captures
+    tracing.info("still inner")
+    inner.assert_logged(message="still inner")
+    outer.assert_not_logged(message="still inner")
+
+    inner.__exit__(None, None, None)
+    outer.__exit__(None, None, None)
+    with pytest.raises(RuntimeError):
+        outer.__exit__(None, None, None)
+
+
+def test_assert_logged_failure(tracing_capture: CaptureLayer) -> None:
+    tracing.info("something else")
+
+    with pytest.raises(AssertionError, match="something else"):
+        tracing_capture.assert_logged(Level.WARN)
+
+
+def test_logging_records(tracing_capture: CaptureLayer) -> None:
+    handler = tracing.LoggingHandler()
+    names = ["test_capture.a", "test_capture.b"]
+    loggers = [logging.getLogger(name) for name in names]
+    for logger in loggers:
+        logger.addHandler(handler)
+        logger.propagate = False
+    try:
+        for _ in range(2):
+            for logger in loggers:
+                logger.warning("from %s", logger.name)
+    finally:
+        for logger in loggers:
+            logger.removeHandler(handler)
+
+    # the records of the same line are told apart by the logger name
+    records = tracing_capture.events
+    assert [record.target for record in records] == names * 2
+    assert [record.message for record in records] == [f"from {x}" for x in names] * 2
+    assert all(record.name == f"event {__file__}" for record in records)
@@ -0,0 +1,56 @@
+import json
+from pathlib import Path
+from typing import Any
+
+import tracing
+from tracing import ChromeTraceLayer
+
+
+@tracing.instrument
+def child(name: str) -> None:
+    tracing.info("working", big=2**70, done=True)
+
+
+@tracing.instrument
+def parent(request_id: int) -> None:
+    child("a b")
+
+
+def load(path: Path) -> list[dict[str, Any]]:
+    # the array is terminated by shutdown()
+    return [event for event in json.loads(path.read_text()) if event["ph"] != "M"]
+
+
+def test_spans_and_events(tmp_path: Path) -> None:
+    path = tmp_path / "trace.json"
+    tracing.init(ChromeTraceLayer(str(path)))
+    parent(1)
+    tracing.shutdown()
+
+    events = load(path)
+    assert [(event["ph"], event["name"]) for event in events] == [
+        ("B", "parent"),
+        ("B", "child"),
+        ("i", "working"),
+        ("E", "child"),
+        ("E", "parent"),
+    ]
+    assert events[0]["args"] == {"request_id": 1}
+    assert events[1]["args"] == {"name": "a b"}
+    # the integers that don't fit into json numbers are strings
+    assert events[2]["args"] == {"message": "working", "big": str(2**70), "done": True}
+    assert events[2]["cat"] == f"{__name__}.child"
+    assert "args" not in events[3]
+
+    timestamps = [event["ts"] for event in events]
+    assert timestamps == sorted(timestamps)
+    assert len({(event["pid"], event["tid"]) for event in events}) == 1
+
+
+def test_without_events(tmp_path: Path) -> None:
+    path = tmp_path / "trace.json"
+    tracing.init(ChromeTraceLayer(str(path), include_events=False))
+    parent(1)
+    tracing.shutdown()
+
+    assert [event["ph"] for event in load(path)] == ["B", "B", "E", "E"]
@@ -0,0 +1,86 @@
+import io
+import json
+import logging
+from datetime import datetime, timezone
+
+import pytest
+
+import tracing
+from tracing import EcsFormat, FmtLayer, Format
+
+
+@tracing.instrument
+def handle(request_id: int) -> None:
+    tracing.warn("slow request", duration=1.5)
+
+
+def log_record(format: Format | EcsFormat) -> dict:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, format=format))
+    handle(42)
+    return json.loads(buffer.getvalue())
+
+
+def test_fields() -> None:
+    before = datetime.now(timezone.utc)
+    record = log_record(Format.ECS)
+    after = datetime.now(timezone.utc)
+
+    # ecs-logging requires these to be the first keys
+    assert list(record)[:3] == ["@timestamp", "log.level", "message"]
+    timestamp = datetime.fromisoformat(record["@timestamp"])
+    assert timestamp.utcoffset() == timezone.utc.utcoffset(None)
+    assert before <= timestamp <= after
+    assert record["log.level"] == "warn"
+    assert record["message"] == "slow request"
+    assert record["ecs.version"] == "8.11.0"
+    assert record["log.logger"] == f"{__name__}.handle"
+    path = log_record.__code__.co_filename
+    # the path relative to the package root
+    assert record["log.origin.file.name"].endswith("test_ecs.py")
+    assert path.endswith(record["log.origin.file.name"])
+    with open(path) as source:
+        lines = source.read().splitlines()
+    assert lines[record["log.origin.file.line"] - 1].strip().startswith("tracing.warn(")
+    # the span fields are merged with the event fields
+    assert record["labels"] == {"request_id": 42, "duration": 1.5}
+
+
+def test_namespace() -> None:
+    record = log_record(EcsFormat(namespace="myapp"))
+
+    assert "labels" not in record
+    assert record["myapp"] == {"request_id": 42, "duration": 1.5}
+
+
+def test_empty_namespace() -> None:
+    with pytest.raises(ValueError, match="namespace"):
+        EcsFormat(namespace="")
+
+
+def test_without_fields() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, format=Format.ECS))
+
+    tracing.info("hello")
+
+    record = json.loads(buffer.getvalue())
+    assert record["message"] == "hello"
+    assert "labels" not in record
+
+
+def test_logging_record() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, format=Format.ECS))
+    logger = logging.getLogger("test_ecs.record")
+    logger.addHandler(tracing.LoggingHandler())
+    logger.propagate = False
+    try:
+        logger.error("failed %s", "twice")
+    finally:
+        logger.handlers.clear()
+
+    record = json.loads(buffer.getvalue())
+    assert record["log.level"] == "error"
+    assert record["message"] == "failed twice"
+    assert record["log.logger"] == "test_ecs.record"
@@ -0,0 +1,131 @@
+import io
+import json
+
+import pytest
+
+import tracing
+from tracing import EnvFilter, FmtLayer, Level
+
+
+def noisy() -> None:
+    tracing.debug("noisy debug")
+
+
+def quiet() -> None:
+    tracing.info("quiet info")
+    tracing.debug("quiet debug")
+
+
+def log_all(filter: str, log_level: Level = Level.INFO) -> str:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, log_level=log_level, filter=filter, with_ansi=False))
+    noisy()
+    quiet()
+    return buffer.getvalue()
+
+
+class Session:
+    def query(self) -> None:
+        tracing.info("query")
+
+
+def test_target_is_module_and_qualname() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, format=tracing.Format.JSON))
+
+    Session().query()
+
+    assert json.loads(buffer.getvalue())["target"] == f"{__name__}.Session.query"
+
+
+def test_log_level_is_used_for_unmatched_targets() -> None:
+    output = log_all(f"{__name__}.noisy=debug")
+
+    assert "noisy debug" in output
+    assert "quiet info" in output
+    assert "quiet debug" not in output
+
+
+def test_bare_level_overrides_log_level() -> None:
+    output = log_all(f"warn,{__name__}.noisy=debug")
+
+    assert "noisy debug" in output
+    assert "quiet info" not in output
+
+
+def test_empty_directives() -> None:
+    output = log_all("")
+
+    assert "quiet info" in output
+    assert "noisy debug" not in output
+
+
+def test_invalid_directives() -> None:
+    with pytest.raises(ValueError, match="invalid filter directives"):
+        EnvFilter("a=notalevel")
+
+
+def test_set_level_skips_replaced_layers() -> None:
+    old_buffer, new_buffer = io.StringIO(), io.StringIO()
+    old = FmtLayer(file=old_buffer, with_ansi=False)
+    tracing.init(old)
+    tracing.init(FmtLayer(file=new_buffer, with_ansi=False))
+
+    tracing.set_level(Level.DEBUG)
+    tracing.init(old)
+    quiet()
+
+    assert "quiet info" in old_buffer.getvalue()
+    assert "quiet debug" not in old_buffer.getvalue()
+
+
+def test_set_level_after_failed_init() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, with_ansi=False))
+    with pytest.raises(TypeError):
+        tracing.init([FmtLayer(), "not a layer"])
+
+    tracing.set_level(Level.DEBUG)
+    quiet()
+
+    assert "quiet debug" in buffer.getvalue()
+
+
+def test_max_level() -> None:
+    buffer = io.StringIO()
+    layer = FmtLayer(file=buffer, log_level=Level.DEBUG, max_level=Level.INFO, with_ansi=False)
+    tracing.init(layer)
+    tracing.warn("quiet warn")
+    quiet()
+
+    output = buffer.getvalue()
+    assert "quiet warn" not in output
+    assert "quiet info" in output
+    assert "quiet debug" in output
+
+
+def test_inverted_level_range() -> None:
+    with pytest.raises(ValueError):
+        FmtLayer(log_level=Level.INFO, max_level=Level.DEBUG)
+
+    layer = FmtLayer(log_level=Level.INFO, max_level=Level.INFO)
+    with pytest.raises(ValueError):
+        layer.set_max_level(Level.DEBUG)
+    layer.set_max_level(Level.ERROR)
+    layer.set_max_level(None)
+
+
+def test_set_level_below_max_level() -> None:
+    buffer = io.StringIO()
+    capped = FmtLayer(file=buffer, log_level=Level.WARN, max_level=Level.WARN, with_ansi=False)
+    other = FmtLayer(file=buffer, log_level=Level.WARN, with_ansi=False)
+    tracing.init([other, capped])
+
+    with pytest.raises(ValueError):
+        capped.set_level(Level.ERROR)
+    # checked before any layer is changed
+    with pytest.raises(ValueError):
+        tracing.set_level(Level.ERROR)
+    tracing.warn("still logged")
+
+    assert buffer.getvalue().count("still logged") == 2
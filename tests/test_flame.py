@@ -0,0 +1,42 @@
+import time
+from pathlib import Path
+
+import tracing
+from tracing import FlameLayer, FlameMode
+
+
+@tracing.instrument
+def child() -> None:
+    time.sleep(0.02)
+
+
+@tracing.instrument
+def parent() -> None:
+    time.sleep(0.02)
+    child()
+
+
+def samples(path: Path) -> dict[str, int]:
+    lines = path.read_text().splitlines()
+    return {
+        stack: int(value) for stack, value in (line.rsplit(" ", 1) for line in lines)
+    }
+
+
+def test_two_layers(tmp_path: Path) -> None:
+    busy, wall = tmp_path / "busy.folded", tmp_path / "wall.folded"
+    tracing.init(
+        [
+            FlameLayer(str(busy)),
+            FlameLayer(str(wall), mode=FlameMode.WALL_CLOCK),
+        ]
+    )
+    parent()
+    tracing.shutdown()
+
+    for path in [busy, wall]:
+        values = samples(path)
+        assert len(values) == 2
+        # each layer counts the time once, not once per layer
+        for value in values.values():
+            assert 15_000_000 < value < 35_000_000
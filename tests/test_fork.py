@@ -0,0 +1,75 @@
+import os
+import threading
+import time
+from pathlib import Path
+
+import pytest
+
+import tracing
+from tracing import FmtLayer, NonBlocking, RollingLog
+
+pytestmark = pytest.mark.skipif(not hasattr(os, "fork"), reason="os.fork() is unix only")
+
+
+def wait(pid: int, timeout: float = 10) -> int:
+    deadline = time.monotonic() + timeout
+    while time.monotonic() < deadline:
+        waited, status = os.waitpid(pid, os.WNOHANG)
+        if waited:
+            return os.waitstatus_to_exitcode(status)
+        time.sleep(0.01)
+    os.kill(pid, 9)
+    os.waitpid(pid, 0)
+    raise AssertionError("the child hasn't exited, a lock is probably held")
+
+
+def fork_and_log(message: str) -> None:
+    pid = os.fork()
+    if pid == 0:
+        try:
+            tracing.reopen_logs()
+            tracing.info(message)
+            tracing.flush()
+        finally:
+            os._exit(0)
+    assert wait(pid) == 0
+
+
+def test_fork_while_other_threads_log(tmp_path: Path) -> None:
+    tracing.init(
+        [
+            FmtLayer(file=str(tmp_path / "blocking.log"), with_ansi=False),
+            FmtLayer(file=RollingLog(str(tmp_path), "rolling.log"), with_ansi=False),
+            FmtLayer(
+                file=str(tmp_path / "non_blocking.log"),
+                non_blocking=NonBlocking(),
+                with_ansi=False,
+            ),
+        ]
+    )
+    stop = threading.Event()
+
+    def log() -> None:
+        while not stop.is_set():
+            tracing.info("thread")
+
+    # reopen_logs() holds its locks without the gil
+    def reopen() -> None:
+        while not stop.is_set():
+            tracing.reopen_logs()
+
+    threads = [threading.Thread(target=log), threading.Thread(target=reopen)]
+    for thread in threads:
+        thread.start()
+    try:
+        for i in range(20):
+            fork_and_log(f"child {i}")
+    finally:
+        stop.set()
+        for thread in threads:
+            thread.join()
+    tracing.flush()
+
+    for name in ["blocking.log", "rolling.log", "non_blocking.log"]:
+        text = (tmp_path / name).read_text()
+        assert all(f"child {i}\n" in text for i in range(20)), name
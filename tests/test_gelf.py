@@ -0,0 +1,18 @@
+import io
+import json
+
+import tracing
+from tracing import FmtLayer, Format
+
+
+def test_additional_field_names() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, format=Format.GELF))
+
+    tracing.info("hello", **{"user.name": "admin", "größe": 1, "id": 2})
+
+    record = json.loads(buffer.getvalue())
+    assert record["short_message"] == "hello"
+    assert record["_user.name"] == "admin"
+    assert record["_gr__e"] == 1
+    assert record["__id"] == 2
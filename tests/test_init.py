@@ -0,0 +1,48 @@
+import io
+import subprocess
+import sys
+
+import tracing
+from tracing import FmtLayer, Level
+
+
+# the filter ids of the reloaded layers are reused, only 64 of them are available
+def test_reinit_with_per_layer_filters() -> None:
+    for _ in range(40):
+        warn = io.StringIO()
+        debug = io.StringIO()
+        tracing.init(
+            [
+                FmtLayer(file=warn, log_level=Level.WARN, with_ansi=False),
+                FmtLayer(file=debug, log_level=Level.DEBUG, with_ansi=False),
+            ]
+        )
+
+    tracing.debug("debug")
+    tracing.warn("warn")
+
+    assert "debug" not in warn.getvalue()
+    assert "warn" in warn.getvalue()
+    assert "debug" in debug.getvalue()
+    assert "warn" in debug.getvalue()
+
+
+def test_exit_removes_the_layers() -> None:
+    script = """
+import atexit
+import tracing
+from tracing import FmtLayer, NonBlocking
+
+# runs after the tracing hook, which is registered by the first init()
+atexit.register(lambda: tracing.info("after exit"))
+tracing.init(FmtLayer(non_blocking=NonBlocking.COMPLETE, with_ansi=False))
+tracing.info("before exit")
+"""
+    result = subprocess.run(
+        [sys.executable, "-c", script], capture_output=True, text=True, timeout=30
+    )
+
+    assert result.returncode == 0
+    assert "before exit" in result.stdout
+    assert "after exit" not in result.stdout
+    assert result.stderr == ""
@@ -0,0 +1,128 @@
+import os
+import socket
+import sys
+from pathlib import Path
+
+import pytest
+
+import tracing
+from tracing import JournaldLayer, instrument
+
+pytestmark = pytest.mark.skipif(
+    not sys.platform.startswith("linux"), reason="journald is linux only"
+)
+
+
+# the values without newlines are `NAME=value`, the test messages don't have others
+def receive(receiver: socket.socket) -> list[tuple[str, str]]:
+    payload = receiver.recv(65536).decode()
+    return [tuple(line.split("=", 1)) for line in payload.splitlines()]  # type: ignore[misc]
+
+
+@pytest.fixture
+def receiver(tmp_path: Path):
+    receiver = socket.socket(socket.AF_UNIX, socket.SOCK_DGRAM)
+    receiver.bind(str(tmp_path / "socket"))
+    receiver.settimeout(5)
+    yield receiver
+    receiver.close()
+
+
+@instrument
+def handle(message: str, request_id: int) -> None:
+    tracing.info("hello", priority="high", user_id=42)
+
+
+def names(fields: list[tuple[str, str]]) -> list[str]:
+    return [name for name, _ in fields]
+
+
+def test_default_prefix(receiver: socket.socket) -> None:
+    tracing.init(JournaldLayer(receiver.getsockname(), syslog_identifier="app"))
+
+    handle("span message", 1)
+
+    fields = receive(receiver)
+    assert ("MESSAGE", "hello") in fields
+    assert ("SYSLOG_IDENTIFIER", "app") in fields
+    assert ("F_MESSAGE", "span message") in fields
+    assert ("F_REQUEST_ID", "1") in fields
+    assert ("F_PRIORITY", "high") in fields
+    assert ("F_USER_ID", "42") in fields
+    assert names(fields).count("MESSAGE") == 1
+    assert names(fields).count("PRIORITY") == 1
+
+
+def test_no_prefix_renames_reserved_fields(receiver: socket.socket) -> None:
+    tracing.init(JournaldLayer(receiver.getsockname(), field_prefix=None))
+
+    handle("span message", 1)
+
+    fields = receive(receiver)
+    assert ("MESSAGE", "hello") in fields
+    assert ("F_MESSAGE", "span message") in fields
+    assert ("F_PRIORITY", "high") in fields
+    assert ("REQUEST_ID", "1") in fields
+    assert ("USER_ID", "42") in fields
+    assert names(fields).count("MESSAGE") == 1
+    assert names(fields).count("PRIORITY") == 1
+
+
+def test_event_fields(receiver: socket.socket) -> None:
+    tracing.init(JournaldLayer(receiver.getsockname(), log_level=tracing.Level.WARN))
+
+    tracing.info("filtered out")
+    line = sys._getframe().f_lineno + 1
+    tracing.error("failed")
+
+    fields = dict(receive(receiver))
+    assert fields["MESSAGE"] == "failed"
+    assert fields["PRIORITY"] == "3"
+    assert fields["SYSLOG_PID"] == str(os.getpid())
+    assert fields["TARGET"].endswith("test_event_fields")
+    assert Path(fields["CODE_FILE"]).name == "test_journald.py"
+    assert fields["CODE_LINE"] == str(line)
+    receiver.settimeout(0.1)
+    with pytest.raises(TimeoutError):
+        receiver.recv(65536)
+
+
+def test_long_and_escaped_names(receiver: socket.socket) -> None:
+    tracing.init(JournaldLayer(receiver.getsockname(), field_prefix=None))
+
+    tracing.info("hello", f_message="user", **{"a" * 70: 1})
+
+    fields = receive(receiver)
+    assert ("MESSAGE", "hello") in fields
+    assert ("F_F_MESSAGE", "user") in fields
+    assert ("A" * 64, "1") in fields
+
+
+def test_long_prefixed_names(receiver: socket.socket) -> None:
+    tracing.init(JournaldLayer(receiver.getsockname()))
+
+    tracing.info("hello", **{"a" * 64: 1})
+
+    assert ("F_" + "A" * 62, "1") in receive(receiver)
+
+
+@pytest.mark.parametrize("log_internal_errors", [True, False])
+def test_send_errors(tmp_path: Path, log_internal_errors: bool) -> None:
+    stderr = os.dup(2)
+    with open(tmp_path / "stderr", "w+") as file:
+        os.dup2(file.fileno(), 2)
+        try:
+            layer = JournaldLayer(
+                str(tmp_path / "missing"), log_internal_errors=log_internal_errors
+            )
+            tracing.init(layer)
+            for _ in range(3):
+                tracing.info("lost")
+        finally:
+            os.dup2(stderr, 2)
+            os.close(stderr)
+        file.seek(0)
+        lines = file.read().splitlines()
+
+    # only the first error of a series is printed
+    assert len(lines) == (1 if log_internal_errors else 0)
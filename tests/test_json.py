@@ -0,0 +1,28 @@
+import io
+import json
+import time
+
+import tracing
+from tracing import Epoch, FmtLayer, JsonFormat, Timer
+
+
+def log_one(**kwargs) -> dict:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, **kwargs))
+    tracing.info("hello")
+    return json.loads(buffer.getvalue())
+
+
+def test_epoch() -> None:
+    before = time.time()
+    record = log_one(format=JsonFormat(epoch=Epoch.MILLISECONDS), timer=Timer.UPTIME)
+    after = time.time()
+
+    assert before * 1000 - 1 <= record["timestamp"] <= after * 1000 + 1
+    assert record["fields"]["message"] == "hello"
+
+
+def test_epoch_without_timer() -> None:
+    record = log_one(format=JsonFormat(epoch=Epoch.SECONDS), timer=None)
+
+    assert "timestamp" not in record
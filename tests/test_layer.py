@@ -0,0 +1,120 @@
+import io
+import sys
+from typing import Any
+
+import tracing
+from tracing import Event, FmtLayer, Level, Span
+
+
+class Recorder(tracing.Layer):
+    def __init__(self, **kwargs: Any) -> None:
+        self.calls: list[tuple[str, str]] = []
+
+    def on_event(self, event: Event) -> None:
+        span = event.span.name if event.span else ""
+        self.calls.append(("event", f"{event.fields['message']} in {span}"))
+
+    def on_new_span(self, span: Span) -> None:
+        self.calls.append(("new_span", span.name))
+
+    def on_enter(self, span: Span) -> None:
+        self.calls.append(("enter", span.name))
+
+    def on_exit(self, span: Span) -> None:
+        self.calls.append(("exit", span.name))
+
+    def on_close(self, span: Span) -> None:
+        self.calls.append(("close", span.name))
+
+
+@tracing.instrument
+def handle(request_id: int) -> None:
+    tracing.info("handled")
+    tracing.debug("details")
+
+
+def test_callbacks() -> None:
+    recorder = Recorder()
+    tracing.init(recorder)
+
+    handle(1)
+
+    assert recorder.calls == [
+        ("new_span", "handle"),
+        ("enter", "handle"),
+        ("event", "handled in handle"),
+        ("exit", "handle"),
+        ("close", "handle"),
+    ]
+
+
+def test_span_fields_and_identity() -> None:
+    spans: list[Span] = []
+
+    class Spans(tracing.Layer):
+        def on_new_span(self, span: Span) -> None:
+            spans.append(span)
+
+        def on_close(self, span: Span) -> None:
+            spans.append(span)
+
+    tracing.init(Spans())
+    handle(42)
+
+    assert spans[0] is spans[1]
+    assert spans[0].fields == {"request_id": 42}
+    assert spans[0].target == f"{__name__}.handle"
+
+
+def test_log_level_and_filter() -> None:
+    debug = Recorder(log_level=Level.DEBUG)
+    filtered = Recorder(filter=f"{__name__}.handle=error")
+    tracing.init([debug, filtered])
+
+    handle(1)
+
+    events = [name for kind, name in debug.calls if kind == "event"]
+    assert events == ["handled in handle", "details in handle"]
+    assert [kind for kind, _ in filtered.calls] == []
+
+
+def test_events_from_callbacks_are_not_passed_back() -> None:
+    buffer = io.StringIO()
+
+    class Echo(tracing.Layer):
+        def __init__(self) -> None:
+            self.events: list[str] = []
+
+        def on_event(self, event: Event) -> None:
+            self.events.append(event.fields["message"])
+            tracing.info(f"echo {event.fields['message']}")
+
+    echo = Echo()
+    tracing.init([FmtLayer(file=buffer, with_ansi=False), echo])
+
+    tracing.info("first")
+    tracing.info("second")
+
+    assert echo.events == ["first", "second"]
+    # the fmt layer still gets them
+    assert "echo first" in buffer.getvalue()
+    assert "echo second" in buffer.getvalue()
+
+
+def test_exception_in_callback() -> None:
+    errors: list[Any] = []
+
+    class Failing(tracing.Layer):
+        def on_event(self, event: Event) -> None:
+            raise ValueError(event.fields["message"])
+
+    hook = sys.unraisablehook
+    sys.unraisablehook = errors.append
+    try:
+        tracing.init(Failing())
+        tracing.info("first")
+        tracing.info("second")
+    finally:
+        sys.unraisablehook = hook
+
+    assert [str(error.exc_value) for error in errors] == ["first", "second"]
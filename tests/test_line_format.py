@@ -0,0 +1,118 @@
+import io
+import logging
+import threading
+
+import pytest
+
+import tracing
+from tracing import FmtLayer, Format
+
+
+def test_format_and_line_format() -> None:
+    with pytest.raises(ValueError):
+        FmtLayer(format=Format.JSON, line_format="%(message)s")
+
+
+def test_thread_is_python_ident() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, line_format="%(thread)d %(message)s"))
+
+    tracing.info("main")
+    thread = threading.Thread(target=lambda: tracing.info("other"))
+    thread.start()
+    thread.join()
+
+    assert buffer.getvalue().splitlines() == [
+        f"{threading.get_ident()} main",
+        f"{thread.ident} other",
+    ]
+
+
+def test_literal_percent_in_brace_template() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, line_format="{message} (%(done)"))
+
+    tracing.info("progress")
+
+    assert buffer.getvalue() == "progress (%(done)\n"
+
+
+def log_from_function() -> None:
+    tracing.info("function")
+
+
+class Handler:
+    def handle(self) -> None:
+        tracing.info("method")
+
+
+def test_func_name() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, line_format="%(funcName)s|%(name)s"))
+
+    log_from_function()
+    Handler().handle()
+
+    assert buffer.getvalue().splitlines() == [
+        f"log_from_function|{__name__}.log_from_function",
+        f"handle|{__name__}.Handler.handle",
+    ]
+
+
+def test_func_name_of_logging_record() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, line_format="[%(funcName)s] %(name)s"))
+    logger = logging.getLogger("test_line_format.record")
+    logger.addHandler(tracing.LoggingHandler())
+    logger.propagate = False
+    try:
+        logger.warning("hello")
+    finally:
+        logger.handlers.clear()
+
+    # the records don't have it
+    assert buffer.getvalue() == "[] test_line_format.record\n"
+
+
+def test_file_placeholders() -> None:
+    buffer = io.StringIO()
+    tracing.init(
+        FmtLayer(file=buffer, line_format="%(module)s|%(filename)s|%(pathname)s")
+    )
+
+    log_from_function()
+
+    path = log_from_function.__code__.co_filename
+    assert buffer.getvalue() == f"test_line_format|test_line_format.py|{path}\n"
+
+
+def test_file_placeholders_of_logging_record() -> None:
+    buffer = io.StringIO()
+    tracing.init(
+        FmtLayer(file=buffer, line_format="%(module)s|%(filename)s|%(pathname)s")
+    )
+    logger = logging.getLogger("test_line_format.record")
+    logger.addHandler(tracing.LoggingHandler())
+    logger.propagate = False
+    try:
+        logger.warning("hello")
+    finally:
+        logger.handlers.clear()
+
+    path = log_from_function.__code__.co_filename
+    assert buffer.getvalue() == f"test_line_format|test_line_format.py|{path}\n"
+
+
+def test_thread_name() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, line_format="%(threadName)s %(message)s"))
+
+    tracing.info("main")
+    thread = threading.Thread(target=lambda: tracing.info("other"), name="worker")
+    thread.start()
+    thread.join()
+
+    assert buffer.getvalue().splitlines() == [
+        f"{threading.current_thread().name} main",
+        "worker other",
+    ]
@@ -0,0 +1,49 @@
+import io
+
+import tracing
+from tracing import FmtLayer, Format
+
+
+def log(**kwargs: object) -> str:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, format=Format.LOGFMT, timer=None, with_target=False))
+    tracing.info("hello world", **kwargs)
+    return buffer.getvalue()
+
+
+def test_plain_values_are_not_quoted() -> None:
+    assert log(user="admin", count=3) == 'level=info msg="hello world" user=admin count=3\n'
+
+
+def test_quoting() -> None:
+    assert log(a="x y", b="k=v", c='say "hi"', d="", e="back\\slash") == (
+        'level=info msg="hello world" a="x y" b="k=v" c="say \\"hi\\"" d="" e=back\\slash\n'
+    )
+
+
+def test_control_characters_are_escaped() -> None:
+    output = log(text="line1\nline2\tend\x01")
+
+    assert output == 'level=info msg="hello world" text="line1\\nline2\\tend\\u0001"\n'
+    assert output.count("\n") == 1
+
+
+@tracing.instrument
+def outer(request_id: int) -> None:
+    inner("a b")
+
+
+@tracing.instrument
+def inner(name: str) -> None:
+    tracing.info("done", user_id=42)
+
+
+def test_span_fields_are_prefixed() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, format=Format.LOGFMT, timer=None, with_target=False))
+
+    outer(1)
+
+    assert buffer.getvalue() == (
+        'level=info msg=done outer.request_id=1 inner.name="a b" user_id=42\n'
+    )
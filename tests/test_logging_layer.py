@@ -0,0 +1,105 @@
+import logging
+from typing import Iterator
+
+import pytest
+
+import tracing
+from tracing import Level, LoggingLayer
+
+
+class Records(logging.Handler):
+    def __init__(self) -> None:
+        super().__init__(level=1)
+        self.records: list[logging.LogRecord] = []
+
+    def emit(self, record: logging.LogRecord) -> None:
+        self.records.append(record)
+
+
+@pytest.fixture
+def records() -> Iterator[list[logging.LogRecord]]:
+    # the events of this module are logged to getLogger(f"{__name__}.<function>"), which propagate here
+    logger = logging.getLogger(__name__)
+    handler = Records()
+    logger.addHandler(handler)
+    logger.setLevel(1)
+    try:
+        yield handler.records
+    finally:
+        logger.removeHandler(handler)
+        logger.setLevel(logging.NOTSET)
+
+
+def log_every_level() -> None:
+    tracing.trace("trace")
+    tracing.debug("debug")
+    tracing.info("info")
+    tracing.warn("warn")
+    tracing.error("error")
+
+
+def test_level_mapping(records: list[logging.LogRecord]) -> None:
+    tracing.init(LoggingLayer(log_level=Level.TRACE))
+
+    log_every_level()
+
+    assert [(r.levelno, r.levelname, r.getMessage()) for r in records] == [
+        (5, "Level 5", "trace"),
+        (logging.DEBUG, "DEBUG", "debug"),
+        (logging.INFO, "INFO", "info"),
+        (logging.WARNING, "WARNING", "warn"),
+        (logging.ERROR, "ERROR", "error"),
+    ]
+    assert {r.name for r in records} == {f"{__name__}.log_every_level"}
+    assert {r.lineno for r in records} == {
+        log_every_level.__code__.co_firstlineno + i for i in range(1, 6)
+    }
+
+
+def test_logger_level_is_respected(records: list[logging.LogRecord]) -> None:
+    tracing.init(LoggingLayer(log_level=Level.TRACE))
+    logging.getLogger(__name__).setLevel(logging.WARNING)
+
+    log_every_level()
+
+    assert [r.getMessage() for r in records] == ["warn", "error"]
+
+
+@tracing.instrument
+def handle(request_id: int, name: str) -> None:
+    tracing.info("handled", user="alice", request_id=7)
+
+
+def test_tracing_fields(records: list[logging.LogRecord]) -> None:
+    tracing.init(LoggingLayer())
+
+    handle(1, "request")
+
+    [record] = records
+    assert record.getMessage() == "handled"
+    # the event fields override the span fields
+    assert record.tracing_fields == {  # type: ignore[attr-defined]
+        "request_id": 7,
+        "name": "request",
+        "user": "alice",
+    }
+    assert record.user == "alice"  # type: ignore[attr-defined]
+    assert record.request_id == 7  # type: ignore[attr-defined]
+    # clashes with LogRecord.name, so it's only in tracing_fields
+    assert record.name == f"{__name__}.handle"
+
+
+def test_events_from_logging_handler_are_not_forwarded_back(
+    records: list[logging.LogRecord],
+) -> None:
+    tracing.init(LoggingLayer())
+    logger = logging.getLogger(f"{__name__}.bridged")
+    handler = tracing.LoggingHandler()
+    logger.addHandler(handler)
+    try:
+        logger.warning("once")
+    finally:
+        logger.removeHandler(handler)
+
+    # the record itself, propagated to the handler of the fixture, and no copy from LoggingLayer
+    assert [r.getMessage() for r in records] == ["once"]
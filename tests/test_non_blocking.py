@@ -0,0 +1,112 @@
+import io
+import os
+import sys
+
+import pytest
+
+import tracing
+from tracing import FmtLayer, NonBlocking
+
+
+def test_config() -> None:
+    non_blocking = NonBlocking(
+        lossy=False,
+        buffered_lines_limit=10,
+        shutdown_timeout=2.5,
+        thread_name="log-writer",
+    )
+
+    assert non_blocking.lossy is False
+    assert non_blocking.buffered_lines_limit == 10
+    assert non_blocking.shutdown_timeout == 2.5
+    assert non_blocking.thread_name == "log-writer"
+    assert repr(non_blocking) == (
+        'NonBlocking(lossy=False, buffered_lines_limit=10, shutdown_timeout=2.5, thread_name="log-writer")'
+    )
+
+
+def test_defaults() -> None:
+    assert NonBlocking.LOSSY.lossy is True
+    assert NonBlocking.COMPLETE.lossy is False
+    for non_blocking in [NonBlocking(), NonBlocking.LOSSY, NonBlocking.COMPLETE]:
+        assert non_blocking.buffered_lines_limit == 128_000
+        assert non_blocking.shutdown_timeout == 1.0
+        assert non_blocking.thread_name == "tracing-appender"
+
+
+def test_invalid_config() -> None:
+    with pytest.raises(ValueError, match="buffered_lines_limit"):
+        NonBlocking(buffered_lines_limit=0)
+    with pytest.raises(ValueError, match="shutdown_timeout"):
+        NonBlocking(shutdown_timeout=-1.0)
+
+
+@pytest.mark.skipif(sys.platform != "linux", reason="reads the thread names from /proc")
+def test_thread_name() -> None:
+    tracing.init(FmtLayer(file=io.StringIO(), non_blocking=NonBlocking(thread_name="log-writer")))
+    # the thread names itself when it starts, the flush waits for it
+    tracing.flush()
+
+    names = set()
+    for task in os.listdir("/proc/self/task"):
+        with open(f"/proc/self/task/{task}/comm") as comm:
+            names.add(comm.read().strip())
+    assert "log-writer" in names
+
+
+def burst(count: int) -> None:
+    # the worker needs the gil to write to the StringIO, so it can't empty the buffer during the burst
+    for i in range(count):
+        tracing.info(f"line {i}")
+    tracing.flush()
+
+
+def test_dropped_lines() -> None:
+    buffer = io.StringIO()
+    layer = FmtLayer(
+        file=buffer,
+        with_ansi=False,
+        non_blocking=NonBlocking(buffered_lines_limit=1),
+    )
+    tracing.init(layer)
+
+    burst(10)
+
+    written = len(buffer.getvalue().splitlines())
+    assert layer.dropped_lines > 0
+    assert written + layer.dropped_lines == 10
+
+    # the counter is kept by the layer, so it goes on over init() calls
+    dropped = layer.dropped_lines
+    tracing.init(layer)
+    burst(10)
+    assert layer.dropped_lines > dropped
+
+
+def test_complete_drops_nothing() -> None:
+    buffer = io.StringIO()
+    layer = FmtLayer(
+        file=buffer,
+        with_ansi=False,
+        non_blocking=NonBlocking(lossy=False, buffered_lines_limit=1),
+    )
+    tracing.init(layer)
+
+    burst(100)
+
+    assert len(buffer.getvalue().splitlines()) == 100
+    assert layer.dropped_lines == 0
+
+
+def test_shared_config_has_a_counter_per_layer() -> None:
+    # the same as passing NonBlocking.LOSSY to both, which is a single shared object
+    lossy = NonBlocking(buffered_lines_limit=1)
+    first = FmtLayer(file=io.StringIO(), non_blocking=lossy)
+    second = FmtLayer(file=io.StringIO(), non_blocking=lossy, filter="off")
+    tracing.init([first, second])
+
+    burst(10)
+
+    assert first.dropped_lines > 0
+    assert second.dropped_lines == 0
+    assert FmtLayer().dropped_lines == 0
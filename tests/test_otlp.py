@@ -0,0 +1,87 @@
+import threading
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from typing import Iterator
+
+import pytest
+
+import tracing
+
+pytestmark = pytest.mark.skipif(
+    not hasattr(tracing, "OtlpLayer"), reason="built without the otlp feature"
+)
+
+
+class Collector(ThreadingHTTPServer):
+    def __init__(self) -> None:
+        super().__init__(("127.0.0.1", 0), CollectorHandler)
+        self.requests: dict[str, list[bytes]] = {}
+
+    @property
+    def endpoint(self) -> str:
+        return f"http://127.0.0.1:{self.server_address[1]}"
+
+
+# accepts the exports and keeps the protobuf payloads; the strings are stored as is in them
+class CollectorHandler(BaseHTTPRequestHandler):
+    server: Collector
+
+    def do_POST(self) -> None:
+        body = self.rfile.read(int(self.headers["Content-Length"]))
+        self.server.requests.setdefault(self.path, []).append(body)
+        self.send_response(200)
+        self.send_header("Content-Type", "application/x-protobuf")
+        self.send_header("Content-Length", "0")
+        self.end_headers()
+
+    def log_message(self, format: str, *args: object) -> None:
+        pass
+
+
+@pytest.fixture
+def collector() -> Iterator[Collector]:
+    collector = Collector()
+    thread = threading.Thread(target=collector.serve_forever)
+    thread.start()
+    yield collector
+    collector.shutdown()
+    thread.join()
+    collector.server_close()
+
+
+@tracing.instrument
+def handle_request(request_id: int) -> None:
+    tracing.info("handled", user="admin")
+
+
+def test_http_export(collector: Collector) -> None:
+    layer = tracing.OtlpLayer(
+        collector.endpoint,
+        service_name="test-service",
+        resource_attributes={"deployment.environment": "test"},
+    )
+    tracing.init(layer)
+
+    handle_request(42)
+    tracing.warn("outside of spans")
+    layer.flush()
+
+    traces = b"".join(collector.requests["/v1/traces"])
+    assert b"test-service" in traces
+    assert b"deployment.environment" in traces
+    assert b"handle_request" in traces
+    assert b"handled" in traces
+    logs = b"".join(collector.requests["/v1/logs"])
+    assert b"test-service" in logs
+    assert b"handled" in logs
+    assert b"outside of spans" in logs
+
+
+def test_without_logs(collector: Collector) -> None:
+    layer = tracing.OtlpLayer(collector.endpoint, export_logs=False)
+    tracing.init(layer)
+
+    handle_request(42)
+    layer.flush()
+
+    assert b"handle_request" in b"".join(collector.requests["/v1/traces"])
+    assert "/v1/logs" not in collector.requests
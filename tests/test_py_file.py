@@ -0,0 +1,84 @@
+import io
+import logging
+
+import tracing
+from tracing import FmtLayer, NonBlocking
+
+
+def test_string_io() -> None:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, with_ansi=False))
+
+    tracing.info("hello")
+
+    assert "hello" in buffer.getvalue()
+
+
+def test_complete_burst_larger_than_the_buffer() -> None:
+    # the worker needs the gil to write to the StringIO, while the logging thread waits for a free slot
+    buffer = io.StringIO()
+    non_blocking = NonBlocking(lossy=False, buffered_lines_limit=8)
+    layer = FmtLayer(file=buffer, with_ansi=False, non_blocking=non_blocking)
+    tracing.init(layer)
+
+    for i in range(1000):
+        tracing.info(f"line {i}")
+    tracing.flush()
+
+    lines = buffer.getvalue().splitlines()
+    assert len(lines) == 1000
+    assert lines[-1].endswith("line 999")
+    assert layer.dropped_lines == 0
+
+
+class LoggingWriter:
+    def __init__(self) -> None:
+        self.lines: list[str] = []
+
+    def write(self, text: str) -> None:
+        self.lines.append(text)
+        tracing.info("written")
+
+
+def test_write_that_logs_blocking() -> None:
+    writer = LoggingWriter()
+    tracing.init(FmtLayer(file=writer, with_ansi=False))
+
+    tracing.info("hello")
+
+    assert len(writer.lines) == 1
+    assert "hello" in writer.lines[0]
+
+
+def test_write_that_logs_non_blocking() -> None:
+    writer = LoggingWriter()
+    tracing.init(FmtLayer(file=writer, with_ansi=False, non_blocking=NonBlocking.COMPLETE))
+
+    tracing.info("hello")
+    tracing.flush()
+
+    assert len(writer.lines) == 1
+    assert "hello" in writer.lines[0]
+
+
+def test_write_that_logs_through_logging_handler() -> None:
+    logger = logging.getLogger("test_py_file")
+    logger.addHandler(tracing.LoggingHandler())
+    logger.propagate = False
+
+    class StdlibWriter:
+        def __init__(self) -> None:
+            self.lines: list[str] = []
+
+        def write(self, text: str) -> None:
+            self.lines.append(text)
+            logger.warning("written")
+
+    writer = StdlibWriter()
+    tracing.init(FmtLayer(file=writer, with_ansi=False))
+    try:
+        tracing.info("hello")
+    finally:
+        logger.handlers.clear()
+
+    assert len(writer.lines) == 1
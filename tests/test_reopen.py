@@ -0,0 +1,56 @@
+import os
+import signal
+import sys
+from pathlib import Path
+from typing import Any, Iterator
+
+import pytest
+
+import tracing
+from tracing import FmtLayer
+
+pytestmark = pytest.mark.skipif(sys.platform == "win32", reason="no SIGHUP")
+
+
+@pytest.fixture
+def restore_sighup() -> Iterator[None]:
+    previous = signal.getsignal(signal.SIGHUP)
+    yield
+    signal.signal(signal.SIGHUP, previous)
+
+
+def test_reopen_after_move(tmp_path: Path) -> None:
+    path = tmp_path / "app.log"
+    tracing.init(FmtLayer(file=str(path), line_format="{message}"))
+    tracing.info("before")
+    path.rename(tmp_path / "app.log.1")
+
+    tracing.reopen_logs()
+    tracing.info("after")
+
+    assert (tmp_path / "app.log.1").read_text() == "before\n"
+    assert path.read_text() == "after\n"
+
+
+def test_sighup_handler_is_installed_once(tmp_path: Path, restore_sighup: None) -> None:
+    calls = []
+
+    def previous(*args: Any) -> None:
+        calls.append(args)
+
+    signal.signal(signal.SIGHUP, previous)
+    path = tmp_path / "app.log"
+    tracing.init(FmtLayer(file=str(path), line_format="{message}"))
+    tracing.info("before")
+
+    tracing.reopen_logs_on_sighup()
+    handler = signal.getsignal(signal.SIGHUP)
+    tracing.reopen_logs_on_sighup(log_internal_errors=False)
+    assert signal.getsignal(signal.SIGHUP) is handler
+
+    path.rename(tmp_path / "app.log.1")
+    os.kill(os.getpid(), signal.SIGHUP)
+    tracing.info("after")
+
+    assert len(calls) == 1
+    assert path.read_text() == "after\n"
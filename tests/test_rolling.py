@@ -0,0 +1,142 @@
+import os
+from pathlib import Path
+
+import pytest
+
+import tracing
+from tracing import Compression, FmtLayer, RollingLog, Rotation
+
+
+def touch(path: Path, mtime: int) -> None:
+    path.write_text("")
+    os.utime(path, (mtime, mtime))
+
+
+def test_prune_keeps_unrelated_files(tmp_path: Path) -> None:
+    unrelated = [
+        "application.jsonl",
+        "app.notes.jsonl",
+        "app.2026-13-01.jsonl",
+        "app.2026-01-01-10.jsonl",
+        "app.2026-01-01.01.jsonl",
+        "app.2026-01-01.jsonl.bak",
+        "app.jsonl",
+    ]
+    for name in unrelated:
+        touch(tmp_path / name, 1)
+    touch(tmp_path / "app.2020-01-01.jsonl", 2)
+    touch(tmp_path / "app.2020-01-02.1.jsonl.gz", 3)
+    touch(tmp_path / "app.2020-01-03.jsonl", 4)
+
+    tracing.init(
+        FmtLayer(
+            file=RollingLog(
+                str(tmp_path), "app", Rotation.DAILY, suffix="jsonl", max_files=3
+            )
+        )
+    )
+
+    names = {path.name for path in tmp_path.iterdir()}
+    assert set(unrelated) <= names
+    assert "app.2020-01-01.jsonl" not in names
+    assert {"app.2020-01-02.1.jsonl.gz", "app.2020-01-03.jsonl"} <= names
+    assert len(names) == len(unrelated) + 3
+
+
+def test_prune_never_rotation(tmp_path: Path) -> None:
+    touch(tmp_path / "app.log.1", 1)
+    touch(tmp_path / "app.log.2", 2)
+    touch(tmp_path / "app.log.old", 1)
+    touch(tmp_path / "app.log.2026-01-01", 1)
+
+    tracing.init(FmtLayer(file=RollingLog(str(tmp_path), "app.log", max_files=2)))
+
+    names = {path.name for path in tmp_path.iterdir()}
+    assert names == {"app.log", "app.log.2", "app.log.old", "app.log.2026-01-01"}
+
+
+def compression_enabled(tmp_path: Path) -> bool:
+    try:
+        RollingLog(str(tmp_path), "app", compression=Compression.GZIP)
+    except ValueError:
+        return False
+    return True
+
+
+def test_compression_leftovers(tmp_path: Path) -> None:
+    if not compression_enabled(tmp_path):
+        pytest.skip("built without the compression feature")
+    touch(tmp_path / "app.2020-01-01.log", 1)
+    touch(tmp_path / "app.2020-01-02.log.gz.tmp", 1)
+    touch(tmp_path / "other.log.gz.tmp", 1)
+
+    tracing.init(
+        FmtLayer(
+            file=RollingLog(
+                str(tmp_path),
+                "app",
+                Rotation.DAILY,
+                suffix="log",
+                compression=Compression.GZIP,
+            )
+        )
+    )
+    tracing.flush()
+
+    names = {path.name for path in tmp_path.iterdir()}
+    assert "app.2020-01-01.log.gz" in names
+    assert "app.2020-01-01.log" not in names
+    assert "app.2020-01-02.log.gz.tmp" not in names
+    assert "other.log.gz.tmp" in names
+
+
+def test_compression_by_size(tmp_path: Path) -> None:
+    if not compression_enabled(tmp_path):
+        pytest.skip("built without the compression feature")
+    tracing.init(
+        FmtLayer(
+            file=RollingLog(
+                str(tmp_path),
+                "app.log",
+                max_size=100,
+                compression=Compression.GZIP,
+            ),
+            with_ansi=False,
+        )
+    )
+
+    for i in range(10):
+        tracing.info(f"line {i}" + "x" * 50)
+    tracing.shutdown()
+
+    names = {path.name for path in tmp_path.iterdir()}
+    assert names == {"app.log.gz"} | {f"app.log.{i}.gz" for i in range(1, 9)} | {
+        "app.log.9"
+    }
+
+
+def test_compression_prunes_while_rotating(tmp_path: Path) -> None:
+    if not compression_enabled(tmp_path):
+        pytest.skip("built without the compression feature")
+    tracing.init(
+        FmtLayer(
+            file=RollingLog(
+                str(tmp_path),
+                "app.log",
+                max_size=100,
+                max_files=2,
+                compression=Compression.GZIP,
+            ),
+            with_ansi=False,
+        )
+    )
+
+    # the compression thread prunes while the writer keeps rotating, the current file is never deleted
+    for i in range(50):
+        tracing.info(f"line {i}" + "x" * 50)
+    tracing.shutdown()
+
+    names = {path.name for path in tmp_path.iterdir()}
+    assert "app.log.49" in names
+    assert "line 49" in (tmp_path / "app.log.49").read_text()
+    assert len(names) == 2
@@ -0,0 +1,45 @@
+import socket
+import sys
+from pathlib import Path
+
+import pytest
+
+import tracing
+from tracing import FmtLayer, NonBlocking, SocketLog
+
+pytestmark = pytest.mark.skipif(sys.platform == "win32", reason="unix sockets")
+
+
+def test_requires_non_blocking(tmp_path: Path) -> None:
+    with pytest.raises(ValueError):
+        tracing.init(FmtLayer(file=SocketLog(str(tmp_path / "log.sock"))))
+
+
+def test_pending_lines_are_sent_at_shutdown(tmp_path: Path) -> None:
+    path = tmp_path / "log.sock"
+    tracing.init(
+        FmtLayer(
+            file=SocketLog(str(path), reconnect_delay=60, max_reconnect_delay=60),
+            non_blocking=NonBlocking.COMPLETE,
+            line_format="{message}",
+        )
+    )
+    tracing.info("before")
+    # the listener isn't there yet, the next attempt is a minute away
+    tracing.flush()
+
+    with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as listener:
+        listener.bind(str(path))
+        listener.listen()
+        tracing.info("after")
+        tracing.flush()
+        tracing.shutdown()
+
+        listener.settimeout(5)
+        connection, _ = listener.accept()
+        with connection:
+            received = b""
+            while chunk := connection.recv(4096):
+                received += chunk
+
+    assert received.decode().splitlines() == ["before", "after"]
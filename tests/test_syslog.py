@@ -0,0 +1,83 @@
+import re
+import socket
+import time
+
+import pytest
+
+import tracing
+from tracing import SyslogFacility, SyslogFormat, SyslogLayer
+
+
+@pytest.mark.parametrize(
+    "format, expected",
+    [
+        (
+            SyslogFormat.RFC5424,
+            r'<132>1 \S+ host app 42 - \[tracing@32473 target="[\w.]*test_udp_message" user="a \\"b\\"\\]"\] hello',
+        ),
+        (
+            SyslogFormat.RFC3164,
+            r'<132>\w{3} [ \d]\d \d\d:\d\d:\d\d host app\[42\]: hello user="a \\"b\\"]"',
+        ),
+    ],
+)
+def test_udp_message(format: SyslogFormat, expected: str) -> None:
+    receiver = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+    receiver.bind(("127.0.0.1", 0))
+    receiver.settimeout(5)
+    port = receiver.getsockname()[1]
+    layer = SyslogLayer(
+        f"udp://127.0.0.1:{port}",
+        format=format,
+        facility=SyslogFacility.LOCAL0,
+        app_name="app",
+        procid="42",
+        hostname="host",
+    )
+    tracing.init(layer)
+
+    tracing.warn("hello", user='a "b"]')
+    tracing.debug("filtered out")
+
+    assert re.fullmatch(expected, receiver.recv(4096).decode())
+    receiver.settimeout(0.1)
+    with pytest.raises(TimeoutError):
+        receiver.recv(4096)
+    assert layer.dropped_messages == 0
+    receiver.close()
+
+
+def test_tcp_reconnect_with_backoff() -> None:
+    listener = socket.create_server(("127.0.0.1", 0))
+    port = listener.getsockname()[1]
+    layer = SyslogLayer(f"tcp://127.0.0.1:{port}")
+    tracing.init(layer)
+    connection, _ = listener.accept()
+
+    tracing.info("hello")
+    connection.settimeout(5)
+    assert b"hello" in connection.recv(4096)
+
+    connection.close()
+    listener.close()
+    start = time.monotonic()
+    for _ in range(100):
+        tracing.info("lost")
+    assert time.monotonic() - start < 5
+    assert layer.dropped_messages > 0
+
+
+@pytest.mark.parametrize("address", ["udp://[::1]", "udp://::1"])
+def test_ipv6_default_port(address: str) -> None:
+    try:
+        receiver = socket.socket(socket.AF_INET6, socket.SOCK_DGRAM)
+        receiver.bind(("::1", 514))
+    except OSError:
+        pytest.skip("can't bind to [::1]:514")
+    receiver.settimeout(5)
+    tracing.init(SyslogLayer(address))
+
+    tracing.info("hello")
+
+    assert b"hello" in receiver.recv(4096)
+    receiver.close()
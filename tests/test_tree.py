@@ -0,0 +1,66 @@
+import io
+import threading
+from typing import Iterator
+
+import tracing
+from tracing import FmtLayer, Format
+
+
+def tree(**kwargs) -> io.StringIO:
+    buffer = io.StringIO()
+    tracing.init(FmtLayer(file=buffer, format=Format.TREE, timer=None, **kwargs))
+    return buffer
+
+
+@tracing.instrument
+def handle() -> None:
+    tracing.info("one")
+    thread = threading.Thread(target=lambda: tracing.info("other"))
+    thread.start()
+    thread.join()
+    tracing.info("two")
+
+
+def test_other_thread_does_not_retrace() -> None:
+    buffer = tree()
+    handle()
+
+    lines = buffer.getvalue().splitlines()
+    assert lines[0].startswith("┌─ ")
+    assert lines[1:4] == ["│   INFO one", " INFO other", "│   INFO two"]
+    assert lines[4].startswith("└─ ")
+
+
+def test_without_level() -> None:
+    buffer = tree(with_level=False)
+    tracing.info("hello")
+
+    assert buffer.getvalue() == "hello\n"
+
+
+@tracing.instrument
+def worker(name: str) -> Iterator[None]:
+    tracing.info("start")
+    yield
+    tracing.info("end")
+
+
+def test_resumed_span_is_retraced_by_each_layer() -> None:
+    buffers = [io.StringIO(), io.StringIO()]
+    tracing.init(
+        [
+            FmtLayer(file=buffer, format=Format.TREE, timer=None, with_ansi=False)
+            for buffer in buffers
+        ]
+    )
+
+    a, b = worker("a"), worker("b")
+    for gen in [a, b, a, b]:
+        next(gen, None)
+
+    lines = buffers[0].getvalue().splitlines()
+    assert lines[:2] == ['┌─ worker name="a"', '┌─ worker name="b"']
+    # the line before each event tells which of the interleaved spans it belongs to
+    assert lines[2:] == ["├┄ worker", "│   INFO start"] * 2 + ["├┄ worker", "│   INFO end"] * 2
+    # the layers keep their last spans separately
+    assert buffers[1].getvalue() == buffers[0].getvalue()
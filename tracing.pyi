@@ -1,5 +1,7 @@
 # Most docstrings are copied from the tracing crate
 
+import logging
+from _typeshed import SupportsWrite
 from typing import Any, Callable, Final, Self, Sequence, TypeVar, overload
 
 try:
@@ -142,20 +144,96 @@ class Format:
     readability.
     """
 
+    LOGFMT: Format
+    """Outputs logfmt lines: `ts=... level=info target=... msg="..." span.key=value key=value`.
+
+    Readable in a terminal and parsed natively by Loki, Grafana and the like.
+    The span fields are prefixed with the span name.
+    """
+
+    ECS: Format
+    """Outputs Elastic Common Schema JSON: `@timestamp`, `log.level`, `message`, `log.logger`, `log.origin.file.*`,
+    the event and span fields are under `labels`; use `EcsFormat` to change that.
+    The timestamp is always in UTC and all the callsite fields are written, `timer` and the `with_*` options are ignored.
+    """
+
+    GELF: Format
+    """Outputs GELF 1.1 JSON, e.g. for Graylog: `short_message`, `level` as a syslog severity,
+    the event and span fields are additional `_`-prefixed fields, with the non-ascii characters replaced by `_`.
+    Like ECS, it ignores `timer` and the `with_*` options.
+    """
+
+    TREE: Format
+    """Multi-line output for local development, the events are indented under their spans,
+    the spans are drawn when they're opened and closed, with their fields and durations.
+    It overrides `fmt_span` with `FmtSpan.NEW | FmtSpan.CLOSE`; `with_level=False` drops the level column.
+    """
+
+class Epoch:
+    SECONDS: Epoch
+    MILLISECONDS: Epoch
+    MICROSECONDS: Epoch
+    NANOSECONDS: Epoch
+
+class JsonFormat:
+    """`Format.JSON` with options, the defaults produce the same output as `Format.JSON`.
+
+    `flatten_event` puts the event fields at the top level instead of under `fields`, `with_current_span` and
+    `with_span_list` add the `span` and `spans` keys. `epoch` writes the timestamp as the number of seconds,
+    milliseconds, etc. since the unix epoch instead of formatting it with the timer."""
+
+    def __new__(
+        cls,
+        *,
+        flatten_event: bool = False,
+        with_current_span: bool = True,
+        with_span_list: bool = True,
+        timestamp_key: str = "timestamp",
+        level_key: str = "level",
+        message_key: str = "message",
+        epoch: Epoch | None = None,
+    ) -> Self: ...
+
+class EcsFormat:
+    """`Format.ECS` with options, `namespace` is the key the event and span fields are put under"""
+
+    def __new__(cls, *, namespace: str = "labels") -> Self: ...
+
 class File:
     STDOUT: File
     STDERR: File
 
 class NonBlocking:
-    """Sets whether logger should be lossy or not.
+    """Non-blocking writer configuration.
 
-    This has effect only if the logger has reached its max capacity, which is 128_000 lines by default"""
+    `lossy` has effect only if the writer has reached its max capacity (`buffered_lines_limit`):
+    if it's true, logs will be dropped, otherwise backpressure will be exerted on senders,
+    blocking them until the buffer has capacity again"""
 
     LOSSY: NonBlocking
-    """logs will be dropped when the buffered limit is reached"""
+    """lossy, with the default settings"""
 
     COMPLETE: NonBlocking
-    """backpressure will be exerted on senders, blocking them until the buffer has capacity again"""
+    """not lossy, with the default settings"""
+
+    def __new__(
+        cls,
+        lossy: bool = True,
+        *,
+        buffered_lines_limit: int = 128_000,
+        shutdown_timeout: float = 1.0,
+        thread_name: str = "tracing-appender",
+    ) -> Self: ...
+    """`shutdown_timeout` is the time in seconds the writer has to write the buffered lines at exit or shutdown"""
+
+    @property
+    def lossy(self) -> bool: ...
+    @property
+    def buffered_lines_limit(self) -> int: ...
+    @property
+    def shutdown_timeout(self) -> float: ...
+    @property
+    def thread_name(self) -> str: ...
 
 class Rotation:
     """Defines a fixed period for rolling of a log file."""
@@ -175,13 +253,32 @@ class Rotation:
     NEVER: Rotation
     """Provides a rotation that never rotates."""
 
+class EnvFilter:
+    """Filters events and spans by their target, level, and span context, like RUST_LOG does.
+
+    Directives are comma-separated, e.g. "warn,myapp.db=debug,myapp.http.client=trace";
+    the target is "<module name>.<function qualname>", and a directive matches every target
+    that starts with it.
+
+    See https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html
+    """
+
+    def __new__(cls, directives: str) -> Self: ...
+    @staticmethod
+    def from_env(var: str = "TRACING_PY_LOG") -> EnvFilter:
+        """Reads the directives from an environment variable; if it isn't set, no directives are used"""
+        ...
+
 class FmtLayer:
     def __new__(
         cls,
         *,
         log_level: Level = Level.INFO,
-        file: str | File | RollingLog = File.STDOUT,
-        format: Format = Format.FULL,
+        max_level: Level | None = None,
+        filter: EnvFilter | str | None = None,
+        file: str | File | RollingLog | SocketLog | SupportsWrite[str] = File.STDOUT,
+        format: Format | JsonFormat | EcsFormat | None = None,
+        line_format: str | None = None,
         fmt_span: FmtSpan = FmtSpan.NONE,
         non_blocking: NonBlocking | None = None,
         log_internal_errors: bool | None = None,
@@ -200,20 +297,407 @@ class FmtLayer:
             creates a filter that passes only the events with level <= log_level,
             ERROR < WARN < INFO < DEBUG < TRACE
 
+        max_level
+            the events and spans with level < max_level are skipped, so log_level=DEBUG, max_level=INFO passes DEBUG and INFO;
+            the skipped spans aren't shown around the events logged inside them either;
+            raises ValueError if it's more verbose than log_level
+
+        filter
+            per-target filter directives, log_level is used for targets not matched by any directive
+
         non_blocking
             if none, tracing will log messages in blocking mode, 
             if passed, I/O will be delegated to a separate non-GIL-bound thread
     """
 
+    def set_level(self, level: Level) -> None: ...
+    """Changes the layer level, takes effect immediately if the layer is already installed; raises ValueError if it's below max_level"""
+
+    def set_max_level(self, level: Level | None) -> None: ...
+    """Changes the most severe level that passes, None removes the bound; raises ValueError if it's more verbose than the layer level"""
+
+    def set_filter(self, filter: EnvFilter | str | None) -> None: ...
+    """Replaces the layer filter directives, takes effect immediately if the layer is already installed"""
+
+    @property
+    def dropped_lines(self) -> int: ...
+    """The number of lines the non-blocking writers of this layer dropped because the buffer was full, counted over every init() call; always 0 if it isn't lossy"""
+
+class Compression:
+    GZIP: Compression
+    ZSTD: Compression
+
 class RollingLog:
+    """Files are named `prefix.date.index.suffix`, the date is omitted for `Rotation.NEVER`,
+    the index is omitted for the first file of the period"""
+
     def __new__(
         cls,
         dir: str,
         prefix: str,
-        rotation: Rotation,
+        rotation: Rotation = Rotation.NEVER,
+        *,
+        suffix: str | None = None,
+        max_size: int | None = None,
+        max_files: int | None = None,
+        compression: Compression | None = None,
+    ) -> Self: ...
+    """`max_size` is in bytes, the file is rotated when it's reached, in addition to the time-based rotation;
+    `max_files` is the number of files that are kept, including the current one, the oldest ones are deleted;
+    if `compression` is set, the rotated files are compressed one at a time in a background thread, `.gz`/`.zst` is appended
+    to their names; the files left uncompressed by a previous process are compressed when the layer is created"""
+
+class SocketLog:
+    """Newline-delimited lines sent to `tcp://host:port`, `unix:///path` or `/path`; the FmtLayer must be non_blocking"""
+
+    def __new__(
+        cls,
+        address: str,
+        *,
+        tls: bool = False,
+        server_name: str | None = None,
+        ca_file: str | None = None,
+        buffer_size: int = 8 * 1024 * 1024,
+        timeout: float = 5.0,
+        reconnect_delay: float = 0.1,
+        max_reconnect_delay: float = 30.0,
+    ) -> Self: ...
+    """`buffer_size` is in bytes, the lines are kept in memory while the socket is disconnected, the oldest ones are dropped when it's full;
+    the reconnect delay starts at `reconnect_delay` and doubles after each failed attempt, up to `max_reconnect_delay`;
+    `server_name` defaults to the host, `ca_file` is a PEM file with the trusted certificates, the webpki roots are used if it's not set"""
+
+class Span:
+    """A span passed to Layer methods; the same object is passed for the whole span lifetime"""
+
+    id: Final[int]
+    level: Final[Level]
+    name: Final[str]
+    target: Final[str]
+    module: Final[str | None]
+    file: Final[str | None]
+    line: Final[int | None]
+    fields: Final[dict[str, Any]]
+    parent: Final[Span | None]
+
+class Event:
+    level: Final[Level]
+    name: Final[str]
+    target: Final[str]
+    module: Final[str | None]
+    """The module name, None for the stdlib logging records"""
+    file: Final[str | None]
+    line: Final[int | None]
+    fields: Final[dict[str, Any]]
+    """Event fields, the message is stored as "message"; values that aren't ints, floats, bools, or strings are passed as their repr()"""
+    span: Final[Span | None]
+    """The current span, if there is one"""
+
+class Layer:
+    """Base class for layers implemented in Python, override the methods you need.
+
+    Exceptions raised in the methods are printed with sys.unraisablehook and otherwise ignored;
+    events emitted from the methods themselves are not passed to Python layers.
+    The level is INFO by default, it can be passed as `log_level` and `filter`, or changed with set_level()/set_filter();
+    the other arguments are ignored, so subclasses can have their own __init__, which receives `log_level` and `filter` too."""
+
+    def __new__(
+        cls,
+        *args: Any,
+        log_level: Level = Level.INFO,
+        filter: EnvFilter | str | None = None,
+        **kwargs: Any,
+    ) -> Self: ...
+    def set_level(self, level: Level) -> None: ...
+    def set_filter(self, filter: EnvFilter | str | None) -> None: ...
+    def on_event(self, event: Event) -> None: ...
+    def on_new_span(self, span: Span) -> None: ...
+    def on_enter(self, span: Span) -> None: ...
+    def on_exit(self, span: Span) -> None: ...
+    def on_close(self, span: Span) -> None: ...
+
+class CapturedRecord:
+    kind: Final[str]
+    """"event", "new_span", "enter", "exit" or "close""""
+    level: Final[Level]
+    name: Final[str]
+    target: Final[str]
+    message: Final[Any | None]
+    fields: Final[dict[str, Any]]
+    """Event fields except the message, or the span fields for span records"""
+    spans: Final[tuple[Span, ...]]
+    """The span chain, outermost span first; for span records the span itself is the last one"""
+
+class CaptureLayer:
+    """Records events and span lifecycle into a list, for use in tests.
+
+    Can be passed to init(), or used as a context manager, which captures the events of the current thread
+    only while the block is active; the layers installed by init() don't receive these events."""
+
+    def __new__(
+        cls,
+        *,
+        log_level: Level = Level.TRACE,
+        filter: EnvFilter | str | None = None,
+    ) -> Self: ...
+    @property
+    def records(self) -> list[CapturedRecord]: ...
+    @property
+    def events(self) -> list[CapturedRecord]: ...
+    def clear(self) -> None: ...
+    def find(
+        self,
+        level: Level | None = None,
+        message: Any | None = None,
+        target: str | None = None,
+        **fields: Any,
+    ) -> list[CapturedRecord]: ...
+    """Returns the events that match all of the passed arguments"""
+
+    def assert_logged(
+        self,
+        level: Level | None = None,
+        message: Any | None = None,
+        target: str | None = None,
+        **fields: Any,
+    ) -> CapturedRecord: ...
+    """Raises AssertionError if there's no matching event, returns the first one otherwise"""
+
+    def assert_not_logged(
+        self,
+        level: Level | None = None,
+        message: Any | None = None,
+        target: str | None = None,
+        **fields: Any,
+    ) -> None: ...
+    def __enter__(self) -> Self: ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> None: ...
+    """Raises RuntimeError if the block isn't the innermost active one on this thread"""
+
+class LoggingLayer:
+    """Forwards the events to logging.getLogger(target), so the existing logging handlers keep working.
+
+    Levels are mapped to the logging ones, TRACE is mapped to 5. The event fields and the fields of its spans
+    are set as record attributes, like `extra` does, except that fields clashing with the record attributes are skipped;
+    all of them are available as record.tracing_fields.
+    Events that come from LoggingHandler are not forwarded back."""
+
+    def __new__(
+        cls,
+        *,
+        log_level: Level = Level.INFO,
+        filter: EnvFilter | str | None = None,
     ) -> Self: ...
+    def set_level(self, level: Level) -> None: ...
+    def set_filter(self, filter: EnvFilter | str | None) -> None: ...
+
+class OtlpProtocol:
+    HTTP_PROTOBUF: OtlpProtocol
+    GRPC: OtlpProtocol
+
+class OtlpLayer:
+    """Exports spans and events to an OpenTelemetry collector.
+
+    Trace and span ids follow the span tree, events are recorded as span events and,
+    if export_logs is set, as log records too, so the events outside spans aren't lost.
+    Only available if the library is built with the "otlp" feature (enabled by default)."""
+
+    def __new__(
+        cls,
+        endpoint: str | None = None,
+        *,
+        protocol: OtlpProtocol = OtlpProtocol.HTTP_PROTOBUF,
+        service_name: str | None = None,
+        resource_attributes: dict[str, str] | None = None,
+        log_level: Level = Level.INFO,
+        filter: EnvFilter | str | None = None,
+        timeout: float = 10.0,
+        max_queue_size: int = 2048,
+        max_export_batch_size: int = 512,
+        scheduled_delay: float = 5.0,
+        export_logs: bool = True,
+    ) -> Self: ...
+    """
+    Creates a new OtlpLayer, the exporters are created by init()
+
+        endpoint
+            collector address, http://localhost:4318 for HTTP and http://localhost:4317 for gRPC by default;
+            /v1/traces and /v1/logs are appended to it when using HTTP
+
+        service_name
+            the service.name resource attribute, OTEL_SERVICE_NAME is used if it's not passed
+
+        timeout, scheduled_delay
+            export timeout and the delay between batch exports, in seconds
+    """
+
+    def set_level(self, level: Level) -> None: ...
+    def set_filter(self, filter: EnvFilter | str | None) -> None: ...
+    def flush(self) -> None: ...
+    """Exports everything that's buffered, blocks until it's done"""
+
+class ChromeTraceLayer:
+    """Writes the spans as Chrome Trace Event Format JSON, which can be opened in Perfetto or chrome://tracing.
+
+    Each span enter/exit is a begin/end event on the thread track, with the span fields as args;
+    generators and coroutines enter their spans each time they're resumed, so their interleaving is visible.
+    Events are written as instant events, unless include_events is False. The file is overwritten."""
+
+    def __new__(
+        cls,
+        path: str,
+        *,
+        log_level: Level = Level.INFO,
+        filter: EnvFilter | str | None = None,
+        include_events: bool = True,
+    ) -> Self: ...
+    def set_level(self, level: Level) -> None: ...
+    def set_filter(self, filter: EnvFilter | str | None) -> None: ...
+
+class FlameMode:
+    WALL_CLOCK: FlameMode
+    """time between span creation and closing, including the time coroutines spend suspended"""
+
+    BUSY: FlameMode
+    """only the time the span was entered"""
+
+class FlameLayer:
+    """Writes folded stack lines (`root;child;grandchild <nanoseconds>`) built from the span hierarchy,
+    which can be rendered by inferno-flamegraph or flamegraph.pl.
+
+    Each span is written once, when it's closed; its value is its own time, without the time of its children.
+    The frames are span targets. The file is overwritten."""
+
+    def __new__(
+        cls,
+        path: str,
+        *,
+        mode: FlameMode = FlameMode.BUSY,
+        log_level: Level = Level.INFO,
+        filter: EnvFilter | str | None = None,
+    ) -> Self: ...
+    def set_level(self, level: Level) -> None: ...
+    def set_filter(self, filter: EnvFilter | str | None) -> None: ...
+
+class SyslogFacility:
+    KERN: SyslogFacility
+    USER: SyslogFacility
+    MAIL: SyslogFacility
+    DAEMON: SyslogFacility
+    AUTH: SyslogFacility
+    SYSLOG: SyslogFacility
+    LPR: SyslogFacility
+    NEWS: SyslogFacility
+    UUCP: SyslogFacility
+    CRON: SyslogFacility
+    AUTHPRIV: SyslogFacility
+    FTP: SyslogFacility
+    LOCAL0: SyslogFacility
+    LOCAL1: SyslogFacility
+    LOCAL2: SyslogFacility
+    LOCAL3: SyslogFacility
+    LOCAL4: SyslogFacility
+    LOCAL5: SyslogFacility
+    LOCAL6: SyslogFacility
+    LOCAL7: SyslogFacility
+
+class SyslogFormat:
+    RFC5424: SyslogFormat
+    """event fields are sent as STRUCTURED-DATA"""
+
+    RFC3164: SyslogFormat
+    """BSD syslog, event fields are appended to the message as key=value pairs"""
+
+class SyslogLayer:
+    """Sends each event as a syslog message.
+
+    `address` is a unix datagram socket path (`/dev/log`, or `unix:///path`), `udp://host:port` or `tcp://host:port`,
+    the port defaults to 514, IPv6 addresses are written in brackets (`udp://[::1]:514`). TCP messages are framed
+    with octet counting (RFC 6587); the host is resolved once, a lost connection is reestablished with an exponential
+    backoff (0.1s to 30s, with a 1s timeout), the messages sent in the meantime are dropped."""
+
+    def __new__(
+        cls,
+        address: str = "/dev/log",
+        *,
+        format: SyslogFormat = SyslogFormat.RFC5424,
+        facility: SyslogFacility = SyslogFacility.USER,
+        app_name: str | None = None,
+        procid: str | None = None,
+        hostname: str | None = None,
+        structured_data_id: str = "tracing@32473",
+        log_level: Level = Level.INFO,
+        filter: EnvFilter | str | None = None,
+    ) -> Self: ...
+    """`app_name` defaults to the script name, `procid` to the current pid, `hostname` to `socket.gethostname()`"""
+
+    def set_level(self, level: Level) -> None: ...
+    def set_filter(self, filter: EnvFilter | str | None) -> None: ...
+    @property
+    def dropped_messages(self) -> int: ...
+    """The number of messages that couldn't be sent, only the first error of a series is printed to stderr"""
+
+class JournaldLayer:
+    """Sends each event to systemd-journald using its native protocol, only available on linux.
+
+    The event fields and the fields of its spans become journal fields, their names are uppercased and the characters
+    other than letters, digits and underscores are replaced with underscores. `MESSAGE`, `PRIORITY`, `SYSLOG_IDENTIFIER`,
+    `SYSLOG_PID`, `TARGET`, `CODE_FILE`, `CODE_LINE` and `CODE_FUNC` are set from the event, the user fields with these
+    names are always prefixed, so they never duplicate them."""
+
+    def __new__(
+        cls,
+        path: str = "/run/systemd/journal/socket",
+        *,
+        syslog_identifier: str | None = None,
+        field_prefix: str | None = "F",
+        log_level: Level = Level.INFO,
+        filter: EnvFilter | str | None = None,
+        log_internal_errors: bool = True,
+    ) -> Self: ...
+    """`syslog_identifier` defaults to the script name; `field_prefix` turns `user_id` into `F_USER_ID`, like in
+    tracing-journald, `field_prefix=None` keeps the names (`USER_ID`), except the reserved ones and the ones starting
+    with `F_`, which get an `F_` prefix; the names are cut to 64 characters.
+    Only the first of a series of send errors is printed to stderr, none if `log_internal_errors` is False"""
+
+    def set_level(self, level: Level) -> None: ...
+    def set_filter(self, filter: EnvFilter | str | None) -> None: ...
+
+AnyLayer = (
+    FmtLayer
+    | Layer
+    | CaptureLayer
+    | LoggingLayer
+    | OtlpLayer
+    | ChromeTraceLayer
+    | FlameLayer
+    | SyslogLayer
+    | JournaldLayer
+)
+
+def init(registry: AnyLayer | Sequence[AnyLayer] | None = None) -> None: ...
+"""Installs the layers; if it's called again, the previous layers are replaced and their workers are stopped"""
+
+def flush() -> None: ...
+"""Blocks until the non-blocking writers have written and the exporters have exported everything logged so far"""
+
+def shutdown() -> None: ...
+"""Removes all layers, flushes and stops their workers and exporters; `init()` may be called again afterwards"""
+
+def reopen_logs() -> None: ...
+"""Reopens the files the `FmtLayer`s write to by path, e.g. after logrotate moved them"""
+
+def reopen_logs_on_sighup(*, log_internal_errors: bool = True) -> None: ...
+"""Installs a SIGHUP handler that calls `reopen_logs()` and then the previous handler; Unix only, must be called from the main thread.
+
+Calling it again while the handler is installed only updates `log_internal_errors`, which makes the handler print the reopen errors to stderr"""
+
+def set_level(level: Level) -> None: ...
+"""Changes the level of every installed layer, filter directives are kept;
+raises ValueError and changes nothing if the level is below the max_level of one of them"""
+
+def set_filter(filter: EnvFilter | str | None) -> None: ...
+"""Replaces the filter directives of every installed layer"""
 
-def init(registry: FmtLayer | Sequence[FmtLayer] | None = None) -> None: ...
 @overload
 def trace(message: Template | str | Any | None = None, **kwargs) -> None: ...
 @overload
@@ -235,6 +719,12 @@ def error(message: Template | str | Any | None = None, **kwargs) -> None: ...
 @overload
 def error(message: str, fmt_args: tuple[Any, ...], **kwargs) -> None: ...
 
+class LoggingHandler(logging.Handler):
+    """Emits stdlib logging records as tracing events, so they go through the installed layers.
+
+    The target is the logger name, the file and the line are taken from the record; levels below DEBUG are mapped to TRACE.
+    The message is formatted only if the event is enabled, handler formatters are not used."""
+
 T = TypeVar("T", bound=Callable)
 
 # is there a way to express dependent types that's supported by most typecheckers? i don't like repeating the signature twice
@@ -0,0 +1,14 @@
+# pytest plugin, registered through the pytest11 entry point
+
+from typing import Iterator
+
+import pytest
+
+import tracing
+
+
+@pytest.fixture
+def tracing_capture() -> Iterator[tracing.CaptureLayer]:
+    """Captures the events emitted by the test, like caplog does for the logging module"""
+    with tracing.CaptureLayer() as capture:
+        yield capture